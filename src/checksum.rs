@@ -0,0 +1,160 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Md5,
+    Crc32c,
+}
+
+impl ChecksumAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Md5 => "md5",
+            Self::Crc32c => "crc32c",
+        }
+    }
+}
+
+/// An entry of a checksum file, e.g. `<hash>  <file>` or `<hash> *<file>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChecksumEntry {
+    pub algorithm: ChecksumAlgorithm,
+    pub hash: String,
+    pub file: Option<String>,
+}
+
+/// Parses a hash prefixed with its algorithm, e.g. `md5:<hash>`, as recorded
+/// from the GCS object metadata of an archive.
+pub fn parse_prefixed_hash(hash: &str) -> Option<ChecksumEntry> {
+    let (algorithm, hash) = [ChecksumAlgorithm::Md5, ChecksumAlgorithm::Crc32c]
+        .into_iter()
+        .find_map(|algorithm| {
+            hash.strip_prefix(algorithm.as_str())
+                .and_then(|hash| hash.strip_prefix(':'))
+                .map(|hash| (algorithm, hash))
+        })?;
+
+    if hash.is_empty() || !hash.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some(ChecksumEntry {
+        algorithm,
+        hash: hash.to_ascii_lowercase(),
+        file: None,
+    })
+}
+
+/// Parses a `sha256sum` style file. Mirrors may rewrite the archive's checksum
+/// files, so binary `*` markers, CRLF line endings, comments and multiple
+/// entries are all accepted, but only as SHA-256 hashes.
+pub fn parse_checksums(content: &str) -> Vec<ChecksumEntry> {
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (hash, file) = match line.split_once(char::is_whitespace) {
+                Some((hash, file)) => (hash, file.trim().trim_start_matches('*')),
+                None => (line, ""),
+            };
+
+            if hash.len() != 64 || !hash.chars().all(|ch| ch.is_ascii_hexdigit()) {
+                return None;
+            }
+
+            Some(ChecksumEntry {
+                algorithm: ChecksumAlgorithm::Sha256,
+                hash: hash.to_ascii_lowercase(),
+                file: (!file.is_empty()).then(|| file.to_owned()),
+            })
+        })
+        .collect()
+}
+
+/// Finds the entry for the given file name. Entries are matched by the last
+/// segment of their path, and a file with a single unnamed entry applies to
+/// any download.
+pub fn find_checksum(content: &str, file_name: &str) -> Option<ChecksumEntry> {
+    let mut entries = parse_checksums(content);
+
+    if let [ChecksumEntry { file: None, .. }] = entries.as_slice() {
+        return entries.pop();
+    }
+
+    entries.into_iter().find(|entry| {
+        entry
+            .file
+            .as_deref()
+            .is_some_and(|file| file.rsplit(['/', '\\']).next() == Some(file_name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "a2df8b5e4b3c6d0a1c3b0b57bd0ed2f3b5f1e3a5cf63b41abbd2a3bd7d9fc0e1";
+
+    fn hash_of(content: &str, file_name: &str) -> Option<String> {
+        find_checksum(content, file_name).map(|entry| entry.hash)
+    }
+
+    #[test]
+    fn parses_dart_archive_format() {
+        assert_eq!(
+            parse_checksums(&format!("{HASH} *dartsdk-linux-x64-release.zip\n")),
+            vec![ChecksumEntry {
+                algorithm: ChecksumAlgorithm::Sha256,
+                hash: HASH.into(),
+                file: Some("dartsdk-linux-x64-release.zip".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn finds_entry_for_file() {
+        let content = format!(
+            "# checksums\r\n{}  dartsdk-linux-arm64-release.zip\r\n{}  ./sdk/dartsdk-linux-x64-release.zip\r\n",
+            "0".repeat(64),
+            HASH.to_uppercase()
+        );
+
+        assert_eq!(
+            hash_of(&content, "dartsdk-linux-x64-release.zip"),
+            Some(HASH.into())
+        );
+        assert_eq!(hash_of(&content, "dartsdk-macos-x64-release.zip"), None);
+    }
+
+    #[test]
+    fn uses_single_unnamed_entry() {
+        assert_eq!(
+            hash_of(&format!("{HASH}\r\n"), "dartsdk-linux-x64-release.zip"),
+            Some(HASH.into())
+        );
+    }
+
+    #[test]
+    fn ignores_non_sha256_entries() {
+        assert_eq!(find_checksum("crc32c:e3069283", "dartsdk.zip"), None);
+        assert_eq!(
+            find_checksum("md5:d41d8cd98f00b204e9800998ecf8427e", "dartsdk.zip"),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_prefixed_hash() {
+        assert_eq!(parse_prefixed_hash("md5:1B2M2Y8AsgTpgAmY7PhCfg=="), None);
+        assert_eq!(parse_prefixed_hash(HASH), None);
+        assert_eq!(
+            parse_prefixed_hash("md5:D41D8CD98F00B204E9800998ECF8427E"),
+            Some(ChecksumEntry {
+                algorithm: ChecksumAlgorithm::Md5,
+                hash: "d41d8cd98f00b204e9800998ecf8427e".into(),
+                file: None,
+            })
+        );
+    }
+}
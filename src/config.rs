@@ -1,13 +1,171 @@
-#[derive(Debug, schematic::Schematic, serde::Deserialize, serde::Serialize)]
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, schematic::Schematic, serde::Deserialize, serde::Serialize)]
 #[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
 pub struct DartPluginConfig {
     pub dist_url: String,
+    pub dist_url_overrides: HashMap<String, String>,
+    pub canary_dist_url: String,
+    pub versions_url: String,
+    pub latest_url: String,
+    pub version_file_url: String,
+    pub canary_latest_url: String,
+    pub placeholders: HashMap<String, HashMap<String, String>>,
+    pub mirrors: Vec<String>,
+    pub checksum_url: Option<String>,
+    pub checksums: HashMap<String, HashMap<String, String>>,
+    pub verify_checksums: bool,
+    pub lockfile: Option<String>,
+    pub metadata_url: String,
+    pub metadata_fallback: bool,
+    pub rosetta_fallback: bool,
+    pub windows_x64_fallback: bool,
+    pub allow_musl: bool,
+    pub native_install: bool,
+    pub system_sdk: bool,
+    pub flutter_sdk: bool,
+    pub flutter_releases_url: String,
+    pub compat_shims: bool,
+}
+
+impl DartPluginConfig {
+    /// Returns a copy of the config with the archive URLs served from the given
+    /// base URL. Per-platform overrides point elsewhere and are kept as is.
+    pub fn with_mirror(&self, base_url: &str) -> Self {
+        Self {
+            dist_url: rebase_url(&self.dist_url, base_url),
+            canary_dist_url: rebase_url(&self.canary_dist_url, base_url),
+            versions_url: rebase_url(&self.versions_url, base_url),
+            latest_url: rebase_url(&self.latest_url, base_url),
+            version_file_url: rebase_url(&self.version_file_url, base_url),
+            canary_latest_url: rebase_url(&self.canary_latest_url, base_url),
+            metadata_url: rebase_url(&self.metadata_url, base_url),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of the config served from a mirror of Flutter's storage,
+    /// which also hosts the Flutter release manifest next to the Dart archive.
+    pub fn with_flutter_mirror(&self, base_url: &str) -> Self {
+        Self {
+            flutter_releases_url: rebase_url(&self.flutter_releases_url, base_url),
+            ..self.with_mirror(base_url)
+        }
+    }
+
+    /// Returns a copy of the config that downloads from the given URL template,
+    /// with the listings served from the host of that URL. The template also
+    /// replaces any per-platform overrides.
+    pub fn with_dist_url(&self, dist_url: &str) -> Self {
+        Self {
+            dist_url: dist_url.to_owned(),
+            dist_url_overrides: HashMap::new(),
+            ..self.with_mirror(&dist_url[..find_path_start(dist_url)])
+        }
+    }
 }
 
 impl Default for DartPluginConfig {
     fn default() -> Self {
         Self {
             dist_url: "https://storage.googleapis.com/dart-archive/channels/{channel}/release/{version}/sdk/dartsdk-{platform}-{arch}-release.zip".into(),
+            dist_url_overrides: HashMap::new(),
+            canary_dist_url: "https://storage.googleapis.com/dart-archive/channels/{channel}/raw/latest/sdk/dartsdk-{platform}-{arch}-release.zip".into(),
+            versions_url: "https://storage.googleapis.com/storage/v1/b/dart-archive/o?delimiter=%2F&prefix=channels%2F{channel}%2Frelease%2F&alt=json".into(),
+            latest_url: "https://storage.googleapis.com/dart-archive/channels/{channel}/release/latest/VERSION".into(),
+            version_file_url: "https://storage.googleapis.com/dart-archive/channels/{channel}/release/{version}/VERSION".into(),
+            canary_latest_url: "https://storage.googleapis.com/dart-archive/channels/{channel}/raw/latest/VERSION".into(),
+            placeholders: HashMap::new(),
+            mirrors: vec![],
+            checksum_url: None,
+            checksums: HashMap::new(),
+            verify_checksums: true,
+            lockfile: None,
+            metadata_url: "https://storage.googleapis.com/storage/v1/b/dart-archive/o/channels%2F{channel}%2Frelease%2F{version}%2Fsdk%2Fdartsdk-{platform}-{arch}-release.zip?alt=json".into(),
+            metadata_fallback: false,
+            rosetta_fallback: false,
+            windows_x64_fallback: false,
+            allow_musl: false,
+            native_install: false,
+            system_sdk: false,
+            flutter_sdk: false,
+            flutter_releases_url: "https://storage.googleapis.com/flutter_infra_release/releases/releases_{platform}.json".into(),
+            compat_shims: false,
         }
     }
 }
+
+// Replaces the scheme and host of the URL, keeping its path and query
+fn rebase_url(url: &str, base_url: &str) -> String {
+    format!(
+        "{}{}",
+        base_url.trim_end_matches('/'),
+        &url[find_path_start(url)..]
+    )
+}
+
+fn find_path_start(url: &str) -> usize {
+    url.find("://")
+        .and_then(|index| url[index + 3..].find('/').map(|path| index + 3 + path))
+        .unwrap_or(url.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebases_urls_on_mirror() {
+        assert_eq!(
+            rebase_url(
+                "https://storage.googleapis.com/dart-archive/channels/{channel}/release/latest/VERSION",
+                "https://storage.flutter-io.cn/"
+            ),
+            "https://storage.flutter-io.cn/dart-archive/channels/{channel}/release/latest/VERSION"
+        );
+        assert_eq!(
+            rebase_url(
+                "https://storage.googleapis.com/storage/v1/b/dart-archive/o?prefix=channels",
+                "http://127.0.0.1:8080/gcs"
+            ),
+            "http://127.0.0.1:8080/gcs/storage/v1/b/dart-archive/o?prefix=channels"
+        );
+    }
+
+    #[test]
+    fn rebases_flutter_releases_on_flutter_mirror_only() {
+        let config = DartPluginConfig::default();
+
+        assert_eq!(
+            config
+                .with_mirror("https://dart-mirror.example.com")
+                .flutter_releases_url,
+            config.flutter_releases_url
+        );
+        assert_eq!(
+            config
+                .with_flutter_mirror("https://storage.flutter-io.cn")
+                .flutter_releases_url,
+            "https://storage.flutter-io.cn/flutter_infra_release/releases/releases_{platform}.json"
+        );
+    }
+
+    #[test]
+    fn serves_listings_from_dist_url_host() {
+        let config = DartPluginConfig {
+            dist_url_overrides: HashMap::from([("linux-x64".into(), "https://other".into())]),
+            ..DartPluginConfig::default()
+        }
+        .with_dist_url("http://127.0.0.1:8080/{version}/dartsdk-{platform}-{arch}.zip");
+
+        assert_eq!(
+            config.dist_url,
+            "http://127.0.0.1:8080/{version}/dartsdk-{platform}-{arch}.zip"
+        );
+        assert!(config.dist_url_overrides.is_empty());
+        assert_eq!(
+            config.latest_url,
+            "http://127.0.0.1:8080/dart-archive/channels/{channel}/release/latest/VERSION"
+        );
+    }
+}
@@ -1,4 +1,47 @@
+use base64::prelude::*;
+use proto_pdk::Version;
 use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::ChecksumAlgorithm;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DartChannel {
+    Stable,
+    Beta,
+    Dev,
+    Main,
+}
+
+impl DartChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+            Self::Dev => "dev",
+            Self::Main => "main",
+        }
+    }
+
+    /// Infers the channel from the prerelease part of a Dart version, which is
+    /// `N.M.beta` or `N.M.dev` for current releases, `dev.N.M` for Dart 1.x/2.0
+    /// dev releases, and `edge.<revision>` for raw builds of the main channel.
+    pub fn from_version(version: &Version) -> Option<Self> {
+        if version.pre.is_empty() {
+            return Some(Self::Stable);
+        }
+
+        version
+            .pre
+            .split('.')
+            .find_map(|identifier| match identifier {
+                "beta" => Some(Self::Beta),
+                "dev" => Some(Self::Dev),
+                "edge" | "main" => Some(Self::Main),
+                _ => None,
+            })
+    }
+}
 
 #[derive(Deserialize, Debug)]
 pub struct DartLatest {
@@ -6,8 +49,98 @@ pub struct DartLatest {
 }
 
 #[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DartObject {
+    pub name: String,
+    pub md5_hash: Option<String>,
+    pub crc32c: Option<String>,
+}
+
+impl DartObject {
+    /// Returns the strongest hash of the object's metadata as hex. GCS encodes
+    /// hashes as base64, and composite objects only have a CRC32C.
+    pub fn checksum(&self) -> Option<(ChecksumAlgorithm, String)> {
+        [
+            (ChecksumAlgorithm::Md5, &self.md5_hash),
+            (ChecksumAlgorithm::Crc32c, &self.crc32c),
+        ]
+        .into_iter()
+        .find_map(|(algorithm, hash)| {
+            let bytes = BASE64_STANDARD.decode(hash.as_deref()?).ok()?;
+
+            Some((
+                algorithm,
+                bytes.iter().map(|byte| format!("{byte:02x}")).collect(),
+            ))
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct DartPrefixes {
+    #[serde(default)]
     pub prefixes: Vec<String>,
+    pub next_page_token: Option<String>,
+}
+
+impl DartPrefixes {
+    /// Collects prefixes across all pages of a GCS listing. The callback receives
+    /// the token of the page to request, or `None` for the first page.
+    pub fn collect_pages<E>(
+        mut fetch_page: impl FnMut(Option<&str>) -> Result<DartPrefixes, E>,
+    ) -> Result<Vec<String>, E> {
+        let mut prefixes = vec![];
+        let mut page_token = None;
+
+        loop {
+            let page = fetch_page(page_token.as_deref())?;
+
+            prefixes.extend(page.prefixes);
+
+            match page.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => break,
+            };
+        }
+
+        Ok(prefixes)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FlutterRelease {
+    pub version: String,
+    pub dart_sdk_version: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FlutterReleases {
+    #[serde(default)]
+    pub releases: Vec<FlutterRelease>,
+}
+
+impl FlutterReleases {
+    /// Maps Flutter versions to the version of their bundled Dart SDK. Prerelease
+    /// builds are listed as `3.6.0 (build 3.6.0-216.1.beta)`, where the build is
+    /// the real version, and the oldest releases don't list it at all.
+    pub fn get_dart_versions(&self) -> BTreeMap<String, String> {
+        self.releases
+            .iter()
+            .filter_map(|release| {
+                let dart_version = release.dart_sdk_version.as_deref()?;
+                let dart_version = match dart_version.split_once("(build ") {
+                    Some((_, build)) => build.trim_end_matches(')'),
+                    None => dart_version,
+                };
+
+                Some((
+                    release.version.clone(),
+                    dart_version.split_whitespace().next()?.to_owned(),
+                ))
+            })
+            .collect()
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -20,3 +153,168 @@ pub struct PubspecYaml {
     pub name: String,
     pub environment: Option<PubspecYamlEnvField>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel_of(version: &str) -> Option<DartChannel> {
+        DartChannel::from_version(&Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn infers_channel_from_version() {
+        assert_eq!(channel_of("3.7.1"), Some(DartChannel::Stable));
+        assert_eq!(channel_of("1.24.3"), Some(DartChannel::Stable));
+        assert_eq!(channel_of("3.8.0-70.1.beta"), Some(DartChannel::Beta));
+        assert_eq!(channel_of("3.0.0-290.2.beta"), Some(DartChannel::Beta));
+        assert_eq!(channel_of("2.19.0-444.1.dev"), Some(DartChannel::Dev));
+        assert_eq!(channel_of("3.9.0-0.0.dev"), Some(DartChannel::Dev));
+        assert_eq!(channel_of("2.0.0-dev.69.5"), Some(DartChannel::Dev));
+        assert_eq!(channel_of("1.24.0-dev.1.0"), Some(DartChannel::Dev));
+        assert_eq!(
+            channel_of("3.10.0-edge.9b6a2e5c2f51c8a91e6de4ac76ac4cc46b3cd6d2"),
+            Some(DartChannel::Main)
+        );
+        assert_eq!(channel_of("3.10.0-rc.1"), None);
+    }
+
+    fn page(prefixes: &[&str], next_page_token: Option<&str>) -> DartPrefixes {
+        DartPrefixes {
+            prefixes: prefixes.iter().map(|prefix| prefix.to_string()).collect(),
+            next_page_token: next_page_token.map(|token| token.to_string()),
+        }
+    }
+
+    #[test]
+    fn collects_prefixes_from_all_pages() {
+        let mut requested = vec![];
+        let prefixes = DartPrefixes::collect_pages(|token| {
+            requested.push(token.map(|token| token.to_string()));
+
+            Ok::<_, ()>(match token {
+                None => page(&["channels/stable/release/3.7.0/"], Some("page-2")),
+                Some("page-2") => page(&[], Some("page-3")),
+                Some("page-3") => page(&["channels/stable/release/3.7.1/"], None),
+                _ => unreachable!(),
+            })
+        })
+        .unwrap();
+
+        assert_eq!(
+            prefixes,
+            vec![
+                "channels/stable/release/3.7.0/",
+                "channels/stable/release/3.7.1/"
+            ]
+        );
+        assert_eq!(
+            requested,
+            vec![None, Some("page-2".into()), Some("page-3".into())]
+        );
+    }
+
+    #[test]
+    fn stops_on_empty_page_token() {
+        let prefixes = DartPrefixes::collect_pages(|token| {
+            Ok::<_, ()>(match token {
+                None => page(&["channels/beta/release/3.8.0-70.1.beta/"], Some("")),
+                _ => unreachable!(),
+            })
+        })
+        .unwrap();
+
+        assert_eq!(prefixes, vec!["channels/beta/release/3.8.0-70.1.beta/"]);
+    }
+
+    #[test]
+    fn propagates_page_errors() {
+        let result = DartPrefixes::collect_pages(|token| match token {
+            None => Ok(page(
+                &["channels/dev/release/2.19.0-444.1.dev/"],
+                Some("page-2"),
+            )),
+            _ => Err("page-2 failed"),
+        });
+
+        assert_eq!(result, Err("page-2 failed"));
+    }
+
+    #[test]
+    fn parses_gcs_listing_page() {
+        let page: DartPrefixes = extism_pdk::json::from_str(
+            r#"{
+                "kind": "storage#objects",
+                "nextPageToken": "Ch9jaGFubmVscy9zdGFibGUvcmVsZWFzZS8xLjI0LjMv",
+                "prefixes": ["channels/stable/release/1.24.2/"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(page.prefixes, vec!["channels/stable/release/1.24.2/"]);
+        assert_eq!(
+            page.next_page_token.as_deref(),
+            Some("Ch9jaGFubmVscy9zdGFibGUvcmVsZWFzZS8xLjI0LjMv")
+        );
+
+        let last_page: DartPrefixes =
+            extism_pdk::json::from_str(r#"{"kind": "storage#objects"}"#).unwrap();
+
+        assert!(last_page.prefixes.is_empty());
+        assert!(last_page.next_page_token.is_none());
+    }
+
+    #[test]
+    fn decodes_object_hashes() {
+        let object: DartObject = extism_pdk::json::from_str(
+            r#"{
+                "kind": "storage#object",
+                "name": "channels/stable/release/1.24.3/sdk/dartsdk-linux-x64-release.zip",
+                "md5Hash": "1B2M2Y8AsgTpgAmY7PhCfg==",
+                "crc32c": "AAAAAA=="
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            object.checksum(),
+            Some((
+                ChecksumAlgorithm::Md5,
+                "d41d8cd98f00b204e9800998ecf8427e".into()
+            ))
+        );
+
+        let composite: DartObject = extism_pdk::json::from_str(
+            r#"{"name": "dartsdk-linux-x64-release.zip", "crc32c": "4waSgw=="}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            composite.checksum(),
+            Some((ChecksumAlgorithm::Crc32c, "e3069283".into()))
+        );
+    }
+
+    #[test]
+    fn maps_flutter_versions_to_dart() {
+        let releases: FlutterReleases = extism_pdk::json::from_str(
+            r#"{
+                "base_url": "https://storage.googleapis.com/flutter_infra_release/releases",
+                "releases": [
+                    {"version": "3.24.3", "channel": "stable", "dart_sdk_version": "3.5.3"},
+                    {"version": "3.27.0-0.2.pre", "channel": "beta", "dart_sdk_version": "3.6.0 (build 3.6.0-334.3.beta)"},
+                    {"version": "v1.0.0", "channel": "stable"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            releases.get_dart_versions(),
+            BTreeMap::from([
+                ("3.24.3".into(), "3.5.3".into()),
+                ("3.27.0-0.2.pre".into(), "3.6.0-334.3.beta".into()),
+            ])
+        );
+    }
+}
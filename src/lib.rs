@@ -1,11 +1,19 @@
+mod checksum;
 mod config;
 mod dart_dist;
+mod lockfile;
+mod native;
+mod template;
 
 #[cfg(feature = "wasm")]
 mod proto;
 
+pub use checksum::*;
 pub use config::*;
 pub use dart_dist::*;
+pub use lockfile::*;
+pub use native::*;
+pub use template::*;
 
 #[cfg(feature = "wasm")]
 pub use proto::*;
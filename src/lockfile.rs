@@ -0,0 +1,37 @@
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct DartLockEntry {
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Archives verified on first install, keyed by version and `{platform}-{arch}`.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct DartLockfile {
+    pub versions: BTreeMap<String, BTreeMap<String, DartLockEntry>>,
+}
+
+impl DartLockfile {
+    pub fn get(&self, version: &str, target: &str) -> Option<&DartLockEntry> {
+        self.versions
+            .get(version)
+            .and_then(|targets| targets.get(target))
+    }
+
+    pub fn insert(&mut self, version: &str, target: &str, entry: DartLockEntry) {
+        self.versions
+            .entry(version.to_owned())
+            .or_default()
+            .insert(target.to_owned(), entry);
+    }
+}
+
+/// An archive to record in the lockfile once its checksum has been verified.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct DartLockPending {
+    pub version: String,
+    pub target: String,
+    pub url: String,
+}
@@ -0,0 +1,333 @@
+use proto_pdk::{HostOS, Version};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DartPackageManager {
+    Apt,
+    Brew,
+    Choco,
+}
+
+impl DartPackageManager {
+    pub fn for_os(os: &HostOS) -> Option<Self> {
+        match os {
+            HostOS::Linux => Some(Self::Apt),
+            HostOS::MacOS => Some(Self::Brew),
+            HostOS::Windows => Some(Self::Choco),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Apt => "apt",
+            Self::Brew => "brew",
+            Self::Choco => "choco",
+        }
+    }
+
+    /// Returns the packages that may provide the version. Homebrew keeps older
+    /// releases in versioned formulae, e.g. `dart@3.6`.
+    pub fn get_package_names(&self, major: u64, minor: u64) -> Vec<String> {
+        match self {
+            Self::Apt => vec!["dart".into()],
+            Self::Brew => vec![
+                "dart-lang/dart/dart".into(),
+                format!("dart-lang/dart/dart@{major}.{minor}"),
+            ],
+            Self::Choco => vec!["dart-sdk".into()],
+        }
+    }
+
+    /// Returns the command that lists the available versions of a package.
+    pub fn get_list_command(&self, name: &str) -> (&'static str, Vec<String>) {
+        match self {
+            Self::Apt => ("apt-cache", vec!["madison".into(), name.into()]),
+            Self::Brew => ("brew", vec!["info".into(), "--json=v1".into(), name.into()]),
+            Self::Choco => (
+                "choco",
+                vec![
+                    "search".into(),
+                    name.into(),
+                    "--exact".into(),
+                    "--all-versions".into(),
+                    "--limit-output".into(),
+                ],
+            ),
+        }
+    }
+
+    /// Parses the output of the list command into package versions, which
+    /// may carry a packaging revision (e.g. `3.7.1-1` for apt).
+    pub fn parse_versions(&self, output: &str) -> Vec<String> {
+        match self {
+            // dart | 3.7.1-1 | https://storage.googleapis.com/download.dartlang.org/linux/debian stable/main amd64 Packages
+            Self::Apt => output
+                .lines()
+                .filter_map(|line| line.split('|').nth(1))
+                .map(|version| version.trim().to_owned())
+                .collect(),
+            Self::Brew => extism_pdk::json::from_str::<Vec<BrewFormula>>(output)
+                .map(|formulae| {
+                    formulae
+                        .into_iter()
+                        .filter_map(|formula| formula.versions.stable)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            // dart-sdk|3.7.1
+            Self::Choco => output
+                .lines()
+                .filter_map(|line| line.split_once('|'))
+                .map(|(_, version)| version.trim().to_owned())
+                .collect(),
+        }
+    }
+
+    /// Finds the package version that provides the Dart version.
+    pub fn find_version<'a>(&self, versions: &'a [String], version: &str) -> Option<&'a String> {
+        versions.iter().find(|package_version| {
+            package_version.as_str() == version
+                || (*self == Self::Apt
+                    && package_version
+                        .rsplit_once('-')
+                        .is_some_and(|(upstream, _)| upstream == version))
+        })
+    }
+
+    /// Returns the command that prints the installed version of a package.
+    pub fn get_installed_command(&self, name: &str) -> (&'static str, Vec<String>) {
+        match self {
+            Self::Apt => (
+                "dpkg-query",
+                vec![
+                    "--show".into(),
+                    "--showformat=${db:Status-Abbrev}${Version}".into(),
+                    name.into(),
+                ],
+            ),
+            Self::Brew => (
+                "brew",
+                vec!["list".into(), "--versions".into(), name.into()],
+            ),
+            Self::Choco => (
+                "choco",
+                vec![
+                    "list".into(),
+                    name.into(),
+                    "--exact".into(),
+                    "--limit-output".into(),
+                ],
+            ),
+        }
+    }
+
+    /// Parses the output of the installed command, if the package is installed.
+    pub fn parse_installed_version(&self, output: &str) -> Option<String> {
+        let version = match self {
+            // ii 3.7.1-1, or rc 3.7.1-1 once removed with its configuration kept
+            Self::Apt => output.strip_prefix("ii")?.trim(),
+            // dart 3.7.1
+            Self::Brew => output.split_whitespace().skip(1).last()?,
+            // dart-sdk|3.7.1
+            Self::Choco => output
+                .lines()
+                .find_map(|line| line.split_once('|'))?
+                .1
+                .trim(),
+        };
+
+        (!version.is_empty()).then(|| version.to_owned())
+    }
+
+    pub fn get_install_command(&self, package: &DartNativePackage) -> (&'static str, Vec<String>) {
+        match self {
+            Self::Apt => (
+                "apt-get",
+                vec![
+                    "install".into(),
+                    "-y".into(),
+                    format!("{}={}", package.name, package.version),
+                ],
+            ),
+            Self::Brew => ("brew", vec!["install".into(), package.name.clone()]),
+            Self::Choco => (
+                "choco",
+                vec![
+                    "install".into(),
+                    package.name.clone(),
+                    "--version".into(),
+                    package.version.clone(),
+                    "-y".into(),
+                ],
+            ),
+        }
+    }
+
+    pub fn get_uninstall_command(
+        &self,
+        package: &DartNativePackage,
+    ) -> (&'static str, Vec<String>) {
+        match self {
+            Self::Apt => (
+                "apt-get",
+                vec!["remove".into(), "-y".into(), package.name.clone()],
+            ),
+            Self::Brew => ("brew", vec!["uninstall".into(), package.name.clone()]),
+            Self::Choco => (
+                "choco",
+                vec!["uninstall".into(), package.name.clone(), "-y".into()],
+            ),
+        }
+    }
+}
+
+/// A package installed by the system package manager, recorded in the tool
+/// directory so that it can be uninstalled later.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct DartNativePackage {
+    pub manager: DartPackageManager,
+    pub name: String,
+    pub version: String,
+}
+
+impl DartNativePackage {
+    /// Whether both are installed to the same location, as every version of a
+    /// package (e.g. apt's `dart` in `/usr/lib/dart`) replaces the previous one.
+    pub fn shares_sdk_with(&self, other: &DartNativePackage) -> bool {
+        self.manager == other.manager && self.name == other.name
+    }
+}
+
+/// A Dart SDK installed outside of proto, e.g. by Homebrew or within a Flutter checkout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DartSystemSdk {
+    pub dir: String,
+    pub version: Version,
+}
+
+/// Parses the version from the output of `dart --version`, which older SDKs
+/// printed as `Dart VM version` to stderr.
+pub fn parse_dart_version(output: &str) -> Option<Version> {
+    output.lines().find_map(|line| {
+        let (_, rest) = line
+            .split_once("Dart SDK version: ")
+            .or_else(|| line.split_once("Dart VM version: "))?;
+
+        Version::parse(rest.split_whitespace().next()?).ok()
+    })
+}
+
+#[derive(Deserialize)]
+struct BrewFormula {
+    versions: BrewFormulaVersions,
+}
+
+#[derive(Deserialize)]
+struct BrewFormulaVersions {
+    stable: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_apt_versions() {
+        let manager = DartPackageManager::Apt;
+        let versions = manager.parse_versions(
+            "      dart |    3.7.1-1 | https://storage.googleapis.com/download.dartlang.org/linux/debian stable/main amd64 Packages\n      dart |    3.7.0-1 | https://storage.googleapis.com/download.dartlang.org/linux/debian stable/main amd64 Packages\n",
+        );
+
+        assert_eq!(versions, vec!["3.7.1-1", "3.7.0-1"]);
+        assert_eq!(
+            manager.find_version(&versions, "3.7.0"),
+            Some(&"3.7.0-1".to_owned())
+        );
+        assert_eq!(manager.find_version(&versions, "3.6.0"), None);
+        assert!(manager.parse_versions("").is_empty());
+    }
+
+    #[test]
+    fn parses_brew_versions() {
+        let manager = DartPackageManager::Brew;
+        let versions = manager.parse_versions(
+            r#"[{"name": "dart@3.6", "versions": {"stable": "3.6.2", "head": null, "bottle": false}}]"#,
+        );
+
+        assert_eq!(versions, vec!["3.6.2"]);
+        assert_eq!(manager.find_version(&versions, "3.6.2-1"), None);
+        assert!(manager
+            .parse_versions("Error: No available formula with the name \"dart@1.0\".")
+            .is_empty());
+    }
+
+    #[test]
+    fn parses_dart_version() {
+        assert_eq!(
+            parse_dart_version(
+                "Dart SDK version: 3.7.1 (stable) (Wed Feb 12 04:50:19 2025 -0800) on \"linux_x64\""
+            ),
+            Some(Version::new(3, 7, 1))
+        );
+        assert_eq!(
+            parse_dart_version(
+                "Dart VM version: 1.24.3 (Wed Dec 13 23:26:59 2017) on \"macos_x64\""
+            ),
+            Some(Version::new(1, 24, 3))
+        );
+        assert_eq!(
+            parse_dart_version("Dart SDK version: 3.8.0-70.1.beta (beta)").map(|v| v.to_string()),
+            Some("3.8.0-70.1.beta".into())
+        );
+        assert_eq!(parse_dart_version("dart: command not found"), None);
+    }
+
+    #[test]
+    fn parses_installed_versions() {
+        assert_eq!(
+            DartPackageManager::Apt.parse_installed_version("ii 3.7.1-1"),
+            Some("3.7.1-1".into())
+        );
+        assert_eq!(
+            DartPackageManager::Apt.parse_installed_version("rc 3.7.1-1"),
+            None
+        );
+        assert_eq!(
+            DartPackageManager::Brew.parse_installed_version("dart 3.7.0 3.7.1\n"),
+            Some("3.7.1".into())
+        );
+        assert_eq!(DartPackageManager::Brew.parse_installed_version(""), None);
+        assert_eq!(
+            DartPackageManager::Choco.parse_installed_version("dart-sdk|3.7.1\r\n"),
+            Some("3.7.1".into())
+        );
+    }
+
+    #[test]
+    fn shares_sdk_between_versions_of_a_package() {
+        let package = |name: &str, version: &str| DartNativePackage {
+            manager: DartPackageManager::Brew,
+            name: name.into(),
+            version: version.into(),
+        };
+
+        assert!(package("dart-lang/dart/dart", "3.7.1")
+            .shares_sdk_with(&package("dart-lang/dart/dart", "3.7.0")));
+        assert!(!package("dart-lang/dart/dart", "3.7.1")
+            .shares_sdk_with(&package("dart-lang/dart/dart@3.6", "3.6.2")));
+    }
+
+    #[test]
+    fn parses_choco_versions() {
+        let manager = DartPackageManager::Choco;
+        let versions = manager.parse_versions("dart-sdk|3.7.1\r\ndart-sdk|3.7.0\r\n");
+
+        assert_eq!(versions, vec!["3.7.1", "3.7.0"]);
+        assert_eq!(
+            manager.find_version(&versions, "3.7.1"),
+            Some(&"3.7.1".to_owned())
+        );
+    }
+}
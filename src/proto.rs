@@ -1,18 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
 use extism_pdk::*;
+use md5::Md5;
 use proto_pdk::*;
 use schematic::SchemaBuilder;
+use sha2::{Digest, Sha256};
 
-use crate::{DartLatest, DartPluginConfig, DartPrefixes, PubspecYaml};
+use crate::{
+    find_checksum, parse_dart_version, parse_prefixed_hash, render_template, ChecksumAlgorithm,
+    DartChannel, DartLatest, DartLockEntry, DartLockPending, DartLockfile, DartNativePackage,
+    DartObject, DartPackageManager, DartPluginConfig, DartPrefixes, DartSystemSdk, FlutterReleases,
+    PubspecYaml,
+};
 
 #[host_fn]
 extern "ExtismHost" {
-    fn exec_command(input: Json<ExecCommandInput>) -> Json<ExecCommandInput>;
+    fn exec_command(input: Json<ExecCommandInput>) -> Json<ExecCommandOutput>;
+    fn host_log(input: Json<HostLogInput>);
+    fn send_request(input: Json<SendRequestInput>) -> Json<SendRequestOutput>;
 }
 
 static NAME: &str = "Dart";
 
+static NATIVE_PACKAGE_FILE: &str = ".native-package.json";
+
 #[plugin_fn]
 pub fn register_tool(Json(_): Json<RegisterToolInput>) -> FnResult<Json<RegisterToolOutput>> {
     Ok(Json(RegisterToolOutput {
@@ -29,10 +43,58 @@ pub fn register_tool(Json(_): Json<RegisterToolInput>) -> FnResult<Json<Register
 #[plugin_fn]
 pub fn load_versions(Json(_): Json<LoadVersionsInput>) -> FnResult<Json<LoadVersionsOutput>> {
     let env = get_host_environment()?;
-    let mut output = LoadVersionsOutput::default();
+    let config = get_dart_config()?;
+
+    check_host_libc(&env, &config)?;
+
+    let mut output = with_mirrors(&config, |config| {
+        let mut output = LoadVersionsOutput::default();
+
+        add_versions_for_channel(DartChannel::Stable, &mut output, &env, config)?;
+        add_versions_for_channel(DartChannel::Beta, &mut output, &env, config)?;
+        add_versions_for_channel(DartChannel::Dev, &mut output, &env, config)?;
+
+        Ok(output)
+    })?;
+
+    // Offered as the "system" alias, which native_install links instead of downloading
+    if config.system_sdk {
+        if let Some(sdk) = detect_system_sdk(&env)? {
+            let version = VersionSpec::Semantic(SemVer(sdk.version));
+
+            output
+                .aliases
+                .insert("system".into(), version.to_unresolved_spec());
+
+            if !output.versions.contains(&version) {
+                output.versions.push(version);
+            }
+        }
+    }
 
-    add_versions_for_channel("stable", &mut output, &env)?;
-    add_versions_for_channel("beta", &mut output, &env)?;
+    Ok(Json(output))
+}
+
+#[plugin_fn]
+pub fn resolve_version(
+    Json(input): Json<ResolveVersionInput>,
+) -> FnResult<Json<ResolveVersionOutput>> {
+    let mut output = ResolveVersionOutput::default();
+
+    // flutter-<version> resolves to the version bundled with that Flutter release
+    if let UnresolvedVersionSpec::Alias(alias) = &input.initial {
+        if let Some(flutter_version) = alias.strip_prefix("flutter-") {
+            let Some(version) = get_flutter_dart_versions()?.remove(flutter_version) else {
+                return Err(plugin_err!(PluginError::Message(format!(
+                    "Unknown Flutter version {flutter_version}, unable to resolve the {NAME} version bundled with it"
+                ))));
+            };
+
+            debug!("Flutter {flutter_version} bundles {NAME}@{version}");
+
+            output.candidate = Some(UnresolvedVersionSpec::parse(version)?);
+        }
+    }
 
     Ok(Json(output))
 }
@@ -41,91 +103,703 @@ pub fn load_versions(Json(_): Json<LoadVersionsInput>) -> FnResult<Json<LoadVers
 pub fn download_prebuilt(
     Json(input): Json<DownloadPrebuiltInput>,
 ) -> FnResult<Json<DownloadPrebuiltOutput>> {
-    let env = get_host_environment()?;
+    let config = get_dart_config()?;
 
     let version_spec = input.context.version;
 
-    check_version_for_os_and_arch(&env, &version_spec)?;
+    // Canary is checked once its version is resolved
+    let env = if version_spec.is_canary() {
+        get_host_environment()?
+    } else {
+        get_target_env(&get_host_environment()?, &version_spec, &config)?
+    };
+
+    let Some(platform) = get_dart_platform(&env.os) else {
+        return Err(PluginError::UnsupportedOS {
+            tool: NAME.to_owned(),
+            os: env.os.to_string(),
+        }
+        .into());
+    };
+
+    let Some(arch) = get_dart_arch(&env.arch) else {
+        return Err(plugin_err!(PluginError::UnsupportedTarget {
+            tool: NAME.to_owned(),
+            arch: env.arch.to_string(),
+            os: env.os.to_string(),
+        }));
+    };
+
+    // Availability only matters when there is another mirror to fall back to
+    let probe_mirrors = config.mirrors.len() > 1;
+
+    let (download_url, checksum_url, fallback, version) = with_mirrors(&config, |config| {
+        // Canary maps to the latest raw build of the "main" channel,
+        // its real version is only known from the build's VERSION file
+        let (version, channel, dist_url) = if version_spec.is_canary() {
+            let latest = fetch_json::<String, DartLatest>(format_url(
+                &config.canary_latest_url,
+                &get_url_values(DartChannel::Main, &env),
+                config,
+            ))?;
+            let Ok(version) = Version::parse(&latest.version) else {
+                return Err(plugin_err!(PluginError::Message(format!(
+                    "Unable to parse {NAME} canary version {}",
+                    latest.version
+                ))));
+            };
+
+            check_version_for_os_and_arch(
+                &env,
+                &VersionSpec::Semantic(SemVer(version.clone())),
+                config,
+            )?;
+
+            (version, DartChannel::Main, &config.canary_dist_url)
+        } else {
+            let version = version_spec.as_version().unwrap().to_owned();
+            let Some(channel) = DartChannel::from_version(&version) else {
+                return Err(plugin_err!(PluginError::Message(format!(
+                    "Unable to determine the release channel of {NAME}@{version}"
+                ))));
+            };
+
+            (version, channel, &config.dist_url)
+        };
+
+        // Releases for a platform may be served from their own location
+        let dist_url = match channel {
+            DartChannel::Main => dist_url,
+            _ => config
+                .dist_url_overrides
+                .get(&format!("{platform}-{arch}"))
+                .unwrap_or(dist_url),
+        };
+
+        let release_dir = if channel != DartChannel::Main && version.major < 2 {
+            resolve_release_dir(channel, &version, &env, config)?
+        } else {
+            version.to_string()
+        };
+
+        let mut values = get_url_values(channel, &env);
+        values.insert("version", release_dir);
+        values.insert("major", version.major.to_string());
+        values.insert("minor", version.minor.to_string());
+        values.insert("patch", version.patch.to_string());
+        values.insert("prerelease", version.pre.to_string());
+
+        let download_url = format_url(dist_url, &values, config);
+        let mut checksum_url = match &config.checksum_url {
+            _ if !config.verify_checksums || !config.checksums.is_empty() => None,
+            Some(template) => Some(format_url(template, &values, config)),
+            None => Some(format!("{}.sha256sum", download_url)),
+        };
+        let mut fallback = None;
+
+        // Without a checksum file, the hash of the archive's object metadata is used.
+        // Requesting the checksum file also tells whether the mirror is available
+        let metadata_checksum_url = checksum_url
+            .clone()
+            .filter(|_| config.metadata_fallback && channel != DartChannel::Main);
+
+        if let Some(url) = metadata_checksum_url {
+            let response = send_request!(&url);
+
+            // Other errors fail the mirror, they don't downgrade the verification
+            if response.status == 404 {
+                let metadata_url = format_url(&config.metadata_url, &values, config);
+                let object = fetch_json::<&String, DartObject>(&metadata_url)?;
+
+                let Some((algorithm, hash)) = object.checksum() else {
+                    return Err(plugin_err!(PluginError::Message(format!(
+                        "No checksum file at {url} and no hash in the metadata of {}",
+                        object.name
+                    ))));
+                };
+
+                host_log!(
+                    stderr,
+                    "No checksum file at {url}, verifying {NAME}@{version} with the {} hash of its archive metadata",
+                    algorithm.as_str()
+                );
+
+                fallback = Some((format!("{}:{hash}", algorithm.as_str()), metadata_url));
+                checksum_url = None;
+            } else if response.status != 200 {
+                return Err(plugin_err!("Failed to request {url} ({})", response.status));
+            }
+        } else if probe_mirrors {
+            let probe_url = match &checksum_url {
+                Some(checksum_url) => Some(checksum_url.to_owned()),
+                // The canary VERSION file was already fetched from this mirror
+                None if channel == DartChannel::Main => None,
+                None => Some(format_url(&config.version_file_url, &values, config)),
+            };
+
+            if let Some(probe_url) = probe_url {
+                fetch(SendRequestInput::new(probe_url))?;
+            }
+        }
+
+        Ok((download_url, checksum_url, fallback, version))
+    })?;
+
+    let mut output = DownloadPrebuiltOutput {
+        download_url,
+        ..DownloadPrebuiltOutput::default()
+    };
+
+    let target = format!("{platform}-{arch}");
+    let lockfile = match &config.lockfile {
+        Some(path) if config.verify_checksums => Some(read_lockfile(path)?),
+        _ => None,
+    };
+    let locked = lockfile
+        .as_ref()
+        .and_then(|lockfile| lockfile.get(&version.to_string(), &target));
+
+    let pinned = config
+        .checksums
+        .get(&version.to_string())
+        .and_then(|checksums| checksums.get(&target));
+
+    var::remove("lock_pending")?;
+    var::remove("metadata_checksum")?;
+
+    if let (Some(locked), Some(pinned)) = (locked, pinned) {
+        if !locked.sha256.eq_ignore_ascii_case(pinned) {
+            return Err(plugin_err!(PluginError::Message(format!(
+                "The pinned checksum of {NAME}@{version} on {target} differs from the lockfile {}, update one of them",
+                config.lockfile.as_deref().unwrap_or_default()
+            ))));
+        }
+    }
+
+    // The source is kept for verify_checksum, which only receives the downloaded files
+    if !config.verify_checksums {
+        debug!("Checksum verification of {NAME}@{version} is disabled");
+    } else if let Some(locked) = locked {
+        var::set(
+            "checksum_source",
+            format!(
+                "the lockfile {}",
+                config.lockfile.as_deref().unwrap_or_default()
+            ),
+        )?;
+        output.checksum = Some(locked.sha256.clone());
+    } else if !config.checksums.is_empty() {
+        let Some(checksum) = pinned else {
+            return Err(plugin_err!(PluginError::Message(format!(
+                "No pinned checksum for {NAME}@{version} on {platform}-{arch}, add it to the checksums setting or remove the setting"
+            ))));
+        };
+
+        var::set("checksum_source", "the pinned checksums")?;
+        output.checksum = Some(checksum.to_owned());
+    } else if let Some(checksum_url) = checksum_url {
+        var::set("checksum_source", &checksum_url)?;
+        output.checksum_url = Some(checksum_url);
+    } else if let Some((checksum, metadata_url)) = fallback {
+        // Only hashes recorded here may use another algorithm than SHA-256
+        var::set("checksum_source", &metadata_url)?;
+        var::set("metadata_checksum", &checksum)?;
+        output.checksum = Some(checksum);
+    }
+
+    if lockfile.is_some() && locked.is_none() {
+        var::set(
+            "lock_pending",
+            Json(DartLockPending {
+                version: version.to_string(),
+                target,
+                url: output.download_url.clone(),
+            }),
+        )?;
+    }
+
+    Ok(Json(output))
+}
+
+#[plugin_fn]
+pub fn verify_checksum(
+    Json(input): Json<VerifyChecksumInput>,
+) -> FnResult<Json<VerifyChecksumOutput>> {
+    let file_name = input
+        .download_file
+        .any_path()
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let source =
+        var::get::<String>("checksum_source")?.unwrap_or_else(|| input.checksum_file.to_string());
+
+    let content = std::fs::read_to_string(&input.checksum_file)?;
+    let expected = match var::get::<String>("metadata_checksum")? {
+        Some(checksum) => parse_prefixed_hash(&checksum),
+        None => find_checksum(&content, &file_name),
+    };
 
-    if version_spec.is_canary() {
+    let Some(expected) = expected else {
         return Err(plugin_err!(PluginError::Message(format!(
-            "{NAME} does not support canary/nightly versions. Please use `proto install dart beta` instead"
+            "Unable to find a checksum for {file_name} in {source}"
+        ))));
+    };
+
+    let actual = hash_file(&input.download_file, expected.algorithm)?;
+
+    if actual != expected.hash {
+        return Err(plugin_err!(PluginError::Message(format!(
+            "Checksum mismatch for {file_name}, expected {} {} but got {actual} (from {source})",
+            expected.algorithm.as_str(),
+            expected.hash
         ))));
     }
 
-    let config = get_tool_config::<DartPluginConfig>()?;
+    if expected.algorithm != ChecksumAlgorithm::Sha256 {
+        host_log!(
+            stderr,
+            "Verified {file_name} with the {} hash from {source}",
+            expected.algorithm.as_str()
+        );
+    }
 
-    let platform = match env.os {
-        HostOS::Linux => "linux",
-        HostOS::MacOS => "macos",
-        HostOS::Windows => "windows",
-        _ => {
-            return Err(PluginError::UnsupportedOS {
-                tool: NAME.to_owned(),
-                os: env.os.to_string(),
-            }
-            .into())
+    // First installs are recorded, later installs are verified against the recorded hash
+    if let Some(Json(pending)) = var::get::<Json<DartLockPending>>("lock_pending")? {
+        let config = get_dart_config()?;
+
+        if let Some(path) = &config.lockfile {
+            let mut lockfile = read_lockfile(path)?;
+
+            lockfile.insert(
+                &pending.version,
+                &pending.target,
+                DartLockEntry {
+                    url: pending.url,
+                    sha256: match expected.algorithm {
+                        ChecksumAlgorithm::Sha256 => actual,
+                        _ => hash_file(&input.download_file, ChecksumAlgorithm::Sha256)?,
+                    },
+                },
+            );
+
+            std::fs::write(get_lockfile_path(path)?, json::to_string_pretty(&lockfile)?)?;
         }
+
+        var::remove("lock_pending")?;
+    }
+
+    Ok(Json(VerifyChecksumOutput { verified: true }))
+}
+
+#[plugin_fn]
+pub fn build_instructions(
+    Json(input): Json<BuildInstructionsInput>,
+) -> FnResult<Json<BuildInstructionsOutput>> {
+    let env = get_host_environment()?;
+
+    // tools/build.py has no target for other architectures, such as ppc64
+    let Some(arch) = get_dart_arch(&env.arch) else {
+        return Err(plugin_err!(PluginError::Message(format!(
+            "Unable to build {NAME} from source for {} on {}, the SDK build only supports the ia32, x64, arm, arm64 and riscv64 architectures",
+            env.arch, env.os
+        ))));
     };
-    let arch = match env.arch {
-        HostArch::Riscv64 => "riscv64",
-        HostArch::X86 => "ia32",
-        HostArch::X64 => "x64",
-        HostArch::Arm => "arm",
-        HostArch::Arm64 => "arm64",
-        _ => {
-            return Err(plugin_err!(PluginError::UnsupportedTarget {
-                tool: NAME.to_owned(),
-                arch: env.arch.to_string(),
-                os: env.os.to_string(),
-            }))
+
+    let revision = match input.context.version.as_version() {
+        Some(version) => format!("sdk@refs/tags/{version}"),
+        None => "sdk@refs/heads/main".into(),
+    };
+
+    // gclient and the build scripts expect depot_tools to be on PATH
+    let depot_tools_dir = into_real_path("/proto/builders/depot_tools")?;
+    let path = match get_host_env_var("PATH")? {
+        Some(path) => format!(
+            "{}{}{path}",
+            depot_tools_dir.display(),
+            env.os.for_native(":", ";")
+        ),
+        None => depot_tools_dir.display().to_string(),
+    };
+    let mut build_env = HashMap::<String, String>::from_iter([("PATH".into(), path)]);
+
+    // Use the locally installed Visual Studio instead of Google's internal toolchain
+    if env.os.is_windows() {
+        build_env.insert("DEPOT_TOOLS_WIN_TOOLCHAIN".into(), "0".into());
+    }
+
+    let out_dir = format!(
+        "sdk/{}/Release{}/dart-sdk",
+        if env.os.is_mac() { "xcodebuild" } else { "out" },
+        arch.to_uppercase()
+    );
+
+    let gclient = |args: Vec<String>| {
+        let mut command = CommandInstruction::with_builder("depot_tools", args);
+        command.env = build_env.clone().into_iter().collect();
+        BuildInstruction::RunCommand(Box::new(command))
+    };
+
+    let python = env.os.for_native("python3", "python");
+    let mut requirements = vec![
+        BuildRequirement::CommandExistsOnPath("git".into()),
+        BuildRequirement::CommandExistsOnPath(python.into()),
+    ];
+
+    if env.os.is_mac() {
+        requirements.push(BuildRequirement::XcodeCommandLineTools);
+    }
+
+    let mut build = CommandInstruction::new(
+        python,
+        [
+            "tools/build.py",
+            "--mode",
+            "release",
+            "--arch",
+            arch,
+            "create_sdk",
+        ],
+    );
+    build.env = build_env.clone().into_iter().collect();
+    build.cwd = Some("sdk".into());
+
+    Ok(Json(BuildInstructionsOutput {
+        help_url: Some("https://github.com/dart-lang/sdk/blob/main/docs/Building.md".into()),
+        requirements,
+        system_dependencies: vec![
+            SystemDependency::for_pm(
+                HostPackageManager::Apt,
+                ["git", "python3", "curl", "xz-utils"],
+            ),
+            SystemDependency::for_pm(HostPackageManager::Dnf, ["git", "python3", "curl", "xz"]),
+            SystemDependency::for_pm(HostPackageManager::Pkg, ["git", "python3", "curl"]),
+            SystemDependency::for_pm(HostPackageManager::Brew, ["git", "python3"]),
+        ],
+        instructions: vec![
+            BuildInstruction::InstallBuilder(Box::new(BuilderInstruction {
+                id: "depot_tools".into(),
+                exe: env.os.for_native("gclient", "gclient.bat").into(),
+                git: GitSource {
+                    url: "https://chromium.googlesource.com/chromium/tools/depot_tools.git".into(),
+                    ..GitSource::default()
+                },
+                ..BuilderInstruction::default()
+            })),
+            gclient(vec![
+                "config".into(),
+                "--name=sdk".into(),
+                "https://dart.googlesource.com/sdk.git".into(),
+            ]),
+            gclient(vec![
+                "sync".into(),
+                "--revision".into(),
+                revision,
+                "--no-history".into(),
+            ]),
+            BuildInstruction::RunCommand(Box::new(build)),
+            BuildInstruction::MoveFile(out_dir.into(), "dart-sdk".into()),
+            BuildInstruction::RemoveAllExcept(vec!["dart-sdk".into()]),
+        ],
+        ..BuildInstructionsOutput::default()
+    }))
+}
+
+#[plugin_fn]
+pub fn native_install(
+    Json(input): Json<NativeInstallInput>,
+) -> FnResult<Json<NativeInstallOutput>> {
+    let env = get_host_environment()?;
+    let config = get_dart_config()?;
+
+    // A system SDK of the requested version is linked instead of downloading another copy
+    if config.system_sdk {
+        if let Some(sdk) = detect_system_sdk(&env)? {
+            if input.context.version.as_version() == Some(&sdk.version) {
+                let result = link_sdk(&env, &input.install_dir, &sdk.dir)?;
+
+                return Ok(Json(NativeInstallOutput {
+                    installed: result.exit_code == 0,
+                    error: (result.exit_code != 0).then(|| {
+                        format!(
+                            "Unable to link the {NAME} SDK at {}: {}",
+                            sdk.dir,
+                            result.get_output()
+                        )
+                    }),
+                    ..NativeInstallOutput::default()
+                }));
+            }
+        }
+    }
+
+    // As does the SDK bundled in a Flutter installed by proto
+    if config.flutter_sdk {
+        if let Some(version) = input.context.version.as_version() {
+            if let Some(sdk_dir) = find_flutter_sdk(version)? {
+                let result = link_sdk(&env, &input.install_dir, &sdk_dir)?;
+
+                return Ok(Json(NativeInstallOutput {
+                    installed: result.exit_code == 0,
+                    error: (result.exit_code != 0).then(|| {
+                        format!(
+                            "Unable to link the {NAME} SDK at {sdk_dir}: {}",
+                            result.get_output()
+                        )
+                    }),
+                    ..NativeInstallOutput::default()
+                }));
+            }
         }
+    }
+
+    // proto prefers a native install over all other strategies, so it's opt-in
+    if !config.native_install {
+        return Ok(Json(NativeInstallOutput {
+            skip_install: true,
+            ..NativeInstallOutput::default()
+        }));
+    }
+
+    let failed = |error: String| {
+        Ok(Json(NativeInstallOutput {
+            error: Some(error),
+            ..NativeInstallOutput::default()
+        }))
     };
-    let version = version_spec.as_version().unwrap();
-    let channel = if version.pre.is_empty() {
-        "stable"
-    } else {
-        "beta"
+
+    let Some(version) = input.context.version.as_version() else {
+        return failed(format!(
+            "{NAME} can only be installed natively for a specific version, not {}",
+            input.context.version
+        ));
     };
 
-    let download_url = config
-        .dist_url
-        .replace("{channel}", channel)
-        .replace("{version}", version.to_string().as_str())
-        .replace("{platform}", platform)
-        .replace("{arch}", arch);
-    let checksum_url = format!("{}.sha256sum", download_url);
+    let Some(manager) = DartPackageManager::for_os(&env.os) else {
+        return failed(format!(
+            "{NAME} can't be installed natively on {}, no supported package manager",
+            env.os
+        ));
+    };
 
-    Ok(Json(DownloadPrebuiltOutput {
-        download_url,
-        checksum_url: Some(checksum_url),
-        ..DownloadPrebuiltOutput::default()
+    if !command_exists(&env, manager.as_str()) {
+        return failed(format!(
+            "{NAME} can't be installed natively, {} isn't available on this machine",
+            manager.as_str()
+        ));
+    }
+
+    let Some(package) = find_native_package(manager, version) else {
+        return failed(format!(
+            "{} can't provide {NAME}@{version}, check the package sources or install another version",
+            manager.as_str()
+        ));
+    };
+
+    // Every version of the package is installed to and linked from the same location
+    let users = find_package_users(&input.install_dir, &package)?;
+
+    if !users.is_empty() {
+        return failed(format!(
+            "{NAME}@{version} can't be installed natively, the {} package of {} is linked by {NAME}@{}. Uninstall that version first",
+            package.name,
+            manager.as_str(),
+            users.join(", ")
+        ));
+    }
+
+    let (command, args) = manager.get_installed_command(&package.name);
+    let installed = exec_command!(raw, command, args)
+        .ok()
+        .filter(|result| result.0.exit_code == 0)
+        .and_then(|result| manager.parse_installed_version(&result.0.stdout));
+
+    if installed
+        .as_ref()
+        .is_some_and(|installed| *installed != package.version)
+    {
+        return failed(format!(
+            "{NAME}@{version} can't be installed natively, {} {} is already installed by {} and would be replaced. Uninstall it first, or disable native-install",
+            package.name,
+            installed.unwrap_or_default(),
+            manager.as_str()
+        ));
+    }
+
+    // A package installed outside of proto is linked, but left in place on uninstall
+    if installed.is_none() {
+        let (command, args) = manager.get_install_command(&package);
+        let result = exec_command!(input, get_native_command(manager, command, args)?);
+
+        if result.exit_code != 0 {
+            return failed(format!(
+                "{} failed to install {NAME}@{version}: {}",
+                manager.as_str(),
+                result.get_output()
+            ));
+        }
+    }
+
+    // proto locates the executables inside the install directory,
+    // so the SDK of the package is linked into it
+    let sdk_dir = get_native_sdk_dir(&package)?;
+    let result = link_sdk(&env, &input.install_dir, &sdk_dir)?;
+
+    if result.exit_code != 0 {
+        return failed(format!(
+            "Unable to link the {NAME} SDK at {sdk_dir}: {}",
+            result.get_output()
+        ));
+    }
+
+    if installed.is_none() {
+        std::fs::write(
+            input.install_dir.join(NATIVE_PACKAGE_FILE),
+            json::to_string(&package)?,
+        )?;
+    }
+
+    Ok(Json(NativeInstallOutput {
+        installed: true,
+        ..NativeInstallOutput::default()
+    }))
+}
+
+#[plugin_fn]
+pub fn native_uninstall(
+    Json(input): Json<NativeUninstallInput>,
+) -> FnResult<Json<NativeUninstallOutput>> {
+    let package_file = input.context.tool_dir.join(NATIVE_PACKAGE_FILE);
+
+    // Installed from an archive, the install directory is all there is to remove
+    if !package_file.exists() {
+        return Ok(Json(NativeUninstallOutput {
+            skip_uninstall: true,
+            ..NativeUninstallOutput::default()
+        }));
+    }
+
+    let package: DartNativePackage = json::from_str(&std::fs::read_to_string(&package_file)?)?;
+    let users = find_package_users(&input.context.tool_dir, &package)?;
+
+    // The package stays installed while other versions still link to it
+    if !users.is_empty() {
+        host_log!(
+            stderr,
+            "Keeping the {} package of {}, it's still linked by {NAME}@{}",
+            package.name,
+            package.manager.as_str(),
+            users.join(", ")
+        );
+
+        return Ok(Json(NativeUninstallOutput {
+            skip_uninstall: true,
+            ..NativeUninstallOutput::default()
+        }));
+    }
+
+    let (command, args) = package.manager.get_uninstall_command(&package);
+    let result = exec_command!(input, get_native_command(package.manager, command, args)?);
+
+    if result.exit_code != 0 {
+        return Ok(Json(NativeUninstallOutput {
+            error: Some(format!(
+                "{} failed to uninstall {}: {}",
+                package.manager.as_str(),
+                package.name,
+                result.get_output()
+            )),
+            ..NativeUninstallOutput::default()
+        }));
+    }
+
+    Ok(Json(NativeUninstallOutput {
+        uninstalled: true,
+        ..NativeUninstallOutput::default()
     }))
 }
 
+// Standalone tools that were replaced by dart subcommands, with the first
+// version shipping them and the first version without them
+const LEGACY_TOOLS: [(&str, Version, Version); 5] = [
+    ("dart2js", Version::new(1, 0, 0), Version::new(2, 18, 0)),
+    (
+        "dartanalyzer",
+        Version::new(1, 0, 0),
+        Version::new(2, 18, 0),
+    ),
+    ("dartdevc", Version::new(1, 24, 0), Version::new(2, 18, 0)),
+    ("dartfmt", Version::new(1, 0, 0), Version::new(2, 15, 0)),
+    ("pub", Version::new(1, 0, 0), Version::new(2, 17, 0)),
+];
+
+// Standalone tools forwarded to the dart subcommands that replaced them,
+// which are available since 2.10.0
+const COMPAT_SHIMS: [(&str, &str); 3] = [
+    ("dartanalyzer", "analyze"),
+    ("dartfmt", "format"),
+    ("pub", "pub"),
+];
+
 #[plugin_fn]
 pub fn locate_executables(
-    Json(_): Json<LocateExecutablesInput>,
+    Json(input): Json<LocateExecutablesInput>,
 ) -> FnResult<Json<LocateExecutablesOutput>> {
     let env = get_host_environment()?;
 
+    // Aliases and canary are the latest SDKs
+    let version = input.context.version.as_version();
+
+    let mut exes = HashMap::from_iter([(
+        "dart".into(),
+        ExecutableConfig::new_primary(
+            env.os
+                .for_native("dart-sdk/bin/dart", "dart-sdk/bin/dart.exe"),
+        ),
+    )]);
+
+    if version.is_none_or(|version| *version >= Version::new(2, 6, 0)) {
+        exes.insert(
+            "dartaotruntime".into(),
+            ExecutableConfig::new(env.os.for_native(
+                "dart-sdk/bin/dartaotruntime",
+                "dart-sdk/bin/dartaotruntime.exe",
+            )),
+        );
+    }
+
+    if let Some(version) = version {
+        // These are shell scripts, or batch files on Windows
+        for (name, added, removed) in LEGACY_TOOLS {
+            if *version >= added && *version < removed {
+                exes.insert(
+                    name.into(),
+                    ExecutableConfig::new(if env.os.is_windows() {
+                        format!("dart-sdk/bin/{name}.bat")
+                    } else {
+                        format!("dart-sdk/bin/{name}")
+                    }),
+                );
+            }
+        }
+    }
+
+    if get_dart_config()?.compat_shims
+        && version.is_none_or(|version| *version >= Version::new(2, 10, 0))
+    {
+        for (name, command) in COMPAT_SHIMS {
+            exes.entry(name.into()).or_insert_with(|| ExecutableConfig {
+                parent_exe_name: Some("dart".into()),
+                shim_before_args: Some(StringOrVec::String(command.into())),
+                ..ExecutableConfig::default()
+            });
+        }
+    }
+
     Ok(Json(LocateExecutablesOutput {
-        exes: HashMap::from_iter([
-            (
-                "dart".into(),
-                ExecutableConfig::new_primary(
-                    env.os
-                        .for_native("dart-sdk/bin/dart", "dart-sdk/bin/dart.exe"),
-                ),
-            ),
-            (
-                "dartaotruntime".into(),
-                ExecutableConfig::new(env.os.for_native(
-                    "dart-sdk/bin/dartaotruntime",
-                    "dart-sdk/bin/dartaotruntime.exe",
-                )),
-            ),
-        ]),
+        exes,
+        // Everything else the SDK ships, for proto activate
+        exes_dirs: vec!["dart-sdk/bin".into(), "dart-sdk/bin/utils".into()],
         globals_lookup_dirs: vec!["$PUB_CACHE/bin".into(), "$HOME/.pub-cache/bin".into()],
         ..LocateExecutablesOutput::default()
     }))
@@ -161,7 +835,10 @@ pub fn parse_version_file(
 pub fn check_version_for_os_and_arch(
     env: &HostEnvironment,
     version_spec: &VersionSpec,
+    config: &DartPluginConfig,
 ) -> FnResult<()> {
+    check_host_libc(env, config)?;
+
     let version = version_spec.as_version().unwrap();
 
     let unresolved_version_spec_option = match env.os {
@@ -272,27 +949,99 @@ pub fn check_version_for_os_and_arch(
     }
 }
 
+// Linux builds of the SDK are linked against glibc and fail to start on musl hosts
+// (e.g. Alpine) with a "not found" error, unless the gcompat layer is installed
+fn check_host_libc(env: &HostEnvironment, config: &DartPluginConfig) -> FnResult<()> {
+    if env.os == HostOS::Linux && env.libc == HostLibc::Musl && !config.allow_musl {
+        return Err(plugin_err!(PluginError::Message(format!(
+            "{NAME} SDK builds for Linux require glibc and don't run on musl hosts such as Alpine Linux. Use a glibc based image, or install gcompat (apk add gcompat) and enable the allow-musl setting"
+        ))));
+    }
+
+    Ok(())
+}
+
+// Resolves the host to download the version for. Old versions without an arm64 build
+// can use the x64 build under Rosetta on Apple Silicon or emulation on Windows when enabled
+fn get_target_env(
+    env: &HostEnvironment,
+    version_spec: &VersionSpec,
+    config: &DartPluginConfig,
+) -> FnResult<HostEnvironment> {
+    let Err(error) = check_version_for_os_and_arch(env, version_spec, config) else {
+        return Ok(env.clone());
+    };
+
+    let (setting, enabled, emulator) = match (&env.os, &env.arch) {
+        (HostOS::MacOS, HostArch::Arm64) => {
+            ("rosetta-fallback", config.rosetta_fallback, "Rosetta")
+        }
+        (HostOS::Windows, HostArch::Arm64) => (
+            "windows-x64-fallback",
+            config.windows_x64_fallback,
+            "emulation",
+        ),
+        _ => return Err(error),
+    };
+
+    let x64_env = HostEnvironment {
+        arch: HostArch::X64,
+        ..env.clone()
+    };
+
+    if check_version_for_os_and_arch(&x64_env, version_spec, config).is_err() {
+        return Err(error);
+    }
+
+    if !enabled {
+        return Err(plugin_err!(PluginError::Message(format!(
+            "{}. Enable the {setting} setting to use the x64 build under {emulator}",
+            error.0
+        ))));
+    }
+
+    debug!("Using the x64 build of {NAME}@{version_spec} under {emulator}");
+
+    Ok(x64_env)
+}
+
 pub fn add_versions_for_channel(
-    channel: &str,
+    channel: DartChannel,
     output: &mut LoadVersionsOutput,
     env: &HostEnvironment,
+    config: &DartPluginConfig,
 ) -> FnResult<()> {
-    let latest = fetch_json::<String, DartLatest>(format!(
-        "https://storage.googleapis.com/dart-archive/channels/{channel}/release/latest/VERSION"
+    let latest = fetch_json::<String, DartLatest>(format_url(
+        &config.latest_url,
+        &get_url_values(channel, env),
+        config,
     ))?;
-    let res = fetch_json::<String, DartPrefixes>(format!("https://storage.googleapis.com/storage/v1/b/dart-archive/o?delimiter=%2F&prefix=channels%2F{channel}%2Frelease%2F&alt=json"))?;
 
-    // Prefixes are GCS paths like "channels/stable/release/3.7.1/"
-    for item in res.prefixes.iter() {
-        let version_as_str = item.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    let release_dirs = fetch_release_dirs(channel, env, config)?;
+
+    // Dart 1.x releases were archived under SVN revision numbers,
+    // their version is only available from the VERSION file inside
+    let revisions = resolve_revisions(channel, &release_dirs, env, config)?;
+
+    // Hosts without prebuilt archives build any version from source
+    let has_prebuilts = get_dart_platform(&env.os).is_some() && get_dart_arch(&env.arch).is_some();
 
-        let Ok(version_spec) = VersionSpec::parse(version_as_str) else {
+    for release_dir in release_dirs {
+        let version_as_str = match revisions.get(&release_dir) {
+            Some(version) => version.to_owned(),
+            None => release_dir,
+        };
+
+        let Ok(version_spec) = VersionSpec::parse(&version_as_str) else {
             continue;
         };
 
-        if version_spec.as_version().is_none()
+        // Skip versions that would be downloaded from another channel
+        if version_spec
+            .as_version()
+            .is_none_or(|version| DartChannel::from_version(version) != Some(channel))
             || output.versions.contains(&version_spec)
-            || check_version_for_os_and_arch(env, &version_spec).is_err()
+            || has_prebuilts && get_target_env(env, &version_spec, config).is_err()
         {
             continue;
         }
@@ -302,9 +1051,9 @@ pub fn add_versions_for_channel(
         if latest.version == version_as_str {
             output
                 .aliases
-                .insert(channel.to_string(), version_spec.to_unresolved_spec());
+                .insert(channel.as_str().into(), version_spec.to_unresolved_spec());
 
-            if channel == "stable" {
+            if channel == DartChannel::Stable {
                 output
                     .aliases
                     .insert("latest".into(), version_spec.to_unresolved_spec());
@@ -315,3 +1064,591 @@ pub fn add_versions_for_channel(
 
     Ok(())
 }
+
+// Mirror environment variables take precedence over the .prototools config,
+// and PROTO_DART_DIST_URL over FLUTTER_STORAGE_BASE_URL for the Dart archive
+fn get_dart_config() -> FnResult<DartPluginConfig> {
+    let mut config = get_tool_config::<DartPluginConfig>()?;
+
+    if let Some(base_url) = get_host_env_var("FLUTTER_STORAGE_BASE_URL")? {
+        config = config.with_flutter_mirror(&base_url);
+        config.mirrors.clear();
+    }
+
+    if let Some(dist_url) = get_host_env_var("PROTO_DART_DIST_URL")? {
+        config = config.with_dist_url(&dist_url);
+        config.mirrors.clear();
+    }
+
+    Ok(config)
+}
+
+// Finds the package providing the version, if any
+fn find_native_package(
+    manager: DartPackageManager,
+    version: &Version,
+) -> Option<DartNativePackage> {
+    manager
+        .get_package_names(version.major, version.minor)
+        .into_iter()
+        .find_map(|name| {
+            let (command, args) = manager.get_list_command(&name);
+            let result = exec_command!(raw, command, args).ok()?.0;
+            let versions = manager.parse_versions(&result.stdout);
+
+            manager
+                .find_version(&versions, &version.to_string())
+                .map(|package_version| DartNativePackage {
+                    manager,
+                    name,
+                    version: package_version.to_owned(),
+                })
+        })
+}
+
+// System packages are installed with root privileges on Linux
+fn get_native_command(
+    manager: DartPackageManager,
+    command: &str,
+    args: Vec<String>,
+) -> FnResult<ExecCommandInput> {
+    if manager == DartPackageManager::Apt && exec_command!(pipe, "id", ["-u"]).stdout.trim() != "0"
+    {
+        return Ok(ExecCommandInput::inherit(
+            "sudo",
+            [command.to_owned()].into_iter().chain(args),
+        ));
+    }
+
+    Ok(ExecCommandInput::inherit(command, args))
+}
+
+// The release manifest is large, so the mapping is kept for the next resolve
+fn get_flutter_dart_versions() -> FnResult<BTreeMap<String, String>> {
+    if let Some(Json(versions)) = var::get::<Json<BTreeMap<String, String>>>("flutter_versions")? {
+        return Ok(versions);
+    }
+
+    let env = get_host_environment()?;
+    let config = get_dart_config()?;
+
+    // Mirrors of the Dart archive don't host it, only FLUTTER_STORAGE_BASE_URL redirects it
+    let releases = fetch_json::<String, FlutterReleases>(format_url(
+        &config.flutter_releases_url,
+        &get_url_values(DartChannel::Stable, &env),
+        &config,
+    ))?;
+    let versions = releases.get_dart_versions();
+
+    var::set("flutter_versions", Json(versions.clone()))?;
+
+    Ok(versions)
+}
+
+// Detects a Dart SDK installed outside of proto, from DART_SDK or the dart on PATH
+fn detect_system_sdk(env: &HostEnvironment) -> FnResult<Option<DartSystemSdk>> {
+    let exe = if env.os.is_windows() {
+        "\\bin\\dart.exe"
+    } else {
+        "/bin/dart"
+    };
+
+    let dir = match get_host_env_var("DART_SDK")? {
+        Some(dir) if !dir.is_empty() => dir,
+        _ => {
+            if !command_exists(env, "dart") {
+                return Ok(None);
+            }
+
+            // The dart on PATH may be a symlink (Homebrew) or a wrapper script
+            // next to the SDK bundled in the cache directory (Flutter)
+            let result = if env.os.is_windows() {
+                exec_command!(
+                    pipe,
+                    "powershell",
+                    [
+                        "-Command",
+                        "$dir = Split-Path (Get-Command dart).Source; if (Test-Path \"$dir\\cache\\dart-sdk\\bin\\dart.exe\") { $dir = \"$dir\\cache\\dart-sdk\\bin\" }; Split-Path $dir",
+                    ]
+                )
+            } else {
+                // Symlinks are followed one by one, as macOS only has readlink -f since 12.3
+                exec_command!(
+                    pipe,
+                    "sh",
+                    [
+                        "-c",
+                        "bin=$(command -v dart); while [ -L \"$bin\" ]; do link=$(readlink \"$bin\"); case $link in /*) bin=$link ;; *) bin=$(dirname \"$bin\")/$link ;; esac; done; dir=$(cd \"$(dirname \"$bin\")\" && pwd -P); if [ -x \"$dir/cache/dart-sdk/bin/dart\" ]; then dir=\"$dir/cache/dart-sdk/bin\"; fi; dirname \"$dir\"",
+                    ]
+                )
+            };
+
+            result.stdout.trim().to_owned()
+        }
+    };
+
+    // The dart on PATH may be the one installed by proto itself
+    if dir.is_empty() || PathBuf::from(&dir).starts_with(into_real_path("/proto")?) {
+        return Ok(None);
+    }
+
+    let bin = format!("{dir}{exe}");
+
+    if !executable_exists(env, &bin) {
+        return Ok(None);
+    }
+
+    let result = exec_command!(pipe, &bin, ["--version"]);
+
+    Ok(parse_dart_version(&result.get_output()).map(|version| DartSystemSdk { dir, version }))
+}
+
+// Unlike command_exists, the path is passed quoted, as SDKs are often installed
+// in directories with spaces, e.g. C:\Users\First Last\flutter
+fn executable_exists(env: &HostEnvironment, bin: &str) -> bool {
+    let result = if env.os.is_windows() {
+        exec_command!(
+            raw,
+            "powershell",
+            [
+                "-Command",
+                format!(
+                    "if (-not (Test-Path -LiteralPath '{}' -PathType Leaf)) {{ exit 1 }}",
+                    bin.replace('\'', "''")
+                )
+                .as_str(),
+            ]
+        )
+    } else {
+        exec_command!(raw, "sh", ["-c", "test -x \"$1\"", "sh", bin])
+    };
+
+    result.is_ok_and(|result| result.0.exit_code == 0)
+}
+
+// Finds the SDK of the version in the cache of a Flutter installed by proto,
+// whose archives extract either flat or into a "flutter" directory
+fn find_flutter_sdk(version: &Version) -> FnResult<Option<String>> {
+    let flutter_dir = PathBuf::from("/proto/tools/flutter");
+
+    if !flutter_dir.exists() {
+        return Ok(None);
+    }
+
+    for entry in std::fs::read_dir(flutter_dir)? {
+        let install_dir = entry?.path();
+
+        for sdk_dir in [
+            install_dir.join("bin/cache/dart-sdk"),
+            install_dir.join("flutter/bin/cache/dart-sdk"),
+        ] {
+            let Ok(sdk_version) = std::fs::read_to_string(sdk_dir.join("version")) else {
+                continue;
+            };
+
+            if Version::parse(sdk_version.trim()).is_ok_and(|sdk_version| &sdk_version == version) {
+                return Ok(Some(
+                    into_real_path(&sdk_dir)?.to_string_lossy().into_owned(),
+                ));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// Finds the other installed versions of the tool that link to the SDK of the package
+fn find_package_users(
+    tool_dir: &VirtualPath,
+    package: &DartNativePackage,
+) -> FnResult<Vec<String>> {
+    let mut users = vec![];
+
+    let Ok(entries) = std::fs::read_dir(tool_dir.parent()) else {
+        return Ok(users);
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let package_file = entry.path().join(NATIVE_PACKAGE_FILE);
+
+        if Some(entry.file_name().as_os_str()) == tool_dir.any_path().file_name()
+            || !package_file.exists()
+        {
+            continue;
+        }
+
+        let other: DartNativePackage = json::from_str(&std::fs::read_to_string(package_file)?)?;
+
+        if other.shares_sdk_with(package) {
+            users.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+
+    users.sort();
+
+    Ok(users)
+}
+
+// Links an SDK into the install directory, where proto locates the executables
+fn link_sdk(
+    env: &HostEnvironment,
+    install_dir: &VirtualPath,
+    sdk_dir: &str,
+) -> FnResult<ExecCommandOutput> {
+    std::fs::create_dir_all(install_dir)?;
+
+    let mut link = if env.os.is_windows() {
+        ExecCommandInput::pipe("cmd", ["/c", "mklink", "/J", "dart-sdk", sdk_dir])
+    } else {
+        ExecCommandInput::pipe("ln", ["-sfn", sdk_dir, "dart-sdk"])
+    };
+    link.working_dir = Some(install_dir.to_owned());
+
+    Ok(exec_command!(input, link))
+}
+
+fn get_native_sdk_dir(package: &DartNativePackage) -> FnResult<String> {
+    let sdk_dir = match package.manager {
+        DartPackageManager::Apt => "/usr/lib/dart".into(),
+        DartPackageManager::Brew => format!(
+            "{}/libexec",
+            exec_command!(pipe, "brew", ["--prefix", &package.name])
+                .stdout
+                .trim()
+        ),
+        DartPackageManager::Choco => format!(
+            "{}\\dart-sdk",
+            get_host_env_var("ChocolateyToolsLocation")?.unwrap_or_else(|| "C:\\tools".into())
+        ),
+    };
+
+    Ok(sdk_dir)
+}
+
+fn hash_file(path: &VirtualPath, algorithm: ChecksumAlgorithm) -> FnResult<String> {
+    let mut file = File::open(path)?;
+
+    let hash = match algorithm {
+        ChecksumAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            format!("{:x}", hasher.finalize())
+        }
+        ChecksumAlgorithm::Md5 => {
+            let mut hasher = Md5::new();
+            io::copy(&mut file, &mut hasher)?;
+            format!("{:x}", hasher.finalize())
+        }
+        ChecksumAlgorithm::Crc32c => {
+            let mut crc = 0;
+            let mut buffer = vec![0; 64 * 1024];
+
+            loop {
+                let read = file.read(&mut buffer)?;
+
+                if read == 0 {
+                    break;
+                }
+
+                crc = crc32c::crc32c_append(crc, &buffer[..read]);
+            }
+
+            format!("{crc:08x}")
+        }
+    };
+
+    Ok(hash)
+}
+
+// Relative paths are resolved against the current directory, which must be the
+// project root, so that running from a subdirectory doesn't start another lockfile
+fn get_lockfile_path(path: &str) -> FnResult<PathBuf> {
+    // Host paths outside of the project aren't accessible to the plugin,
+    // including Windows paths, which the plugin doesn't see as absolute
+    if Path::new(path).is_absolute() || path.starts_with('\\') || path.get(1..2) == Some(":") {
+        return Err(plugin_err!(PluginError::Message(format!(
+            "Lockfile {path} must be relative to the project root"
+        ))));
+    }
+
+    let lockfile_path = PathBuf::from("/cwd").join(path);
+
+    if !lockfile_path.exists() && !PathBuf::from("/cwd/.prototools").exists() {
+        return Err(plugin_err!(PluginError::Message(format!(
+            "Lockfile {path} not found in the current directory, run proto from the project root that configures it"
+        ))));
+    }
+
+    Ok(lockfile_path)
+}
+
+fn read_lockfile(path: &str) -> FnResult<DartLockfile> {
+    let path = get_lockfile_path(path)?;
+
+    if !path.exists() {
+        return Ok(DartLockfile::default());
+    }
+
+    Ok(json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+// Runs the operation against each configured mirror in order until one succeeds,
+// or once against the configured URLs when there are no mirrors. Only failed
+// requests and unreadable responses move on to the next mirror
+fn with_mirrors<T>(
+    config: &DartPluginConfig,
+    mut op: impl FnMut(&DartPluginConfig) -> FnResult<T>,
+) -> FnResult<T> {
+    if config.mirrors.is_empty() {
+        return op(config);
+    }
+
+    let mut skipped = vec![];
+
+    for mirror in &config.mirrors {
+        match op(&config.with_mirror(mirror)) {
+            Ok(result) => {
+                if skipped.is_empty() {
+                    debug!("Using {NAME} mirror {mirror}");
+                } else {
+                    host_log!(
+                        stderr,
+                        "Using {NAME} mirror {mirror}, skipped {}",
+                        skipped.join(", ")
+                    );
+                }
+
+                return Ok(result);
+            }
+            // Errors raised by the plugin itself would fail on every mirror
+            Err(error) if error.0.downcast_ref::<PluginError>().is_some() => {
+                return Err(error);
+            }
+            Err(error) => {
+                debug!("Skipping {NAME} mirror {mirror}: {}", error.0);
+
+                skipped.push(format!("{mirror} ({})", error.0));
+            }
+        };
+    }
+
+    Err(plugin_err!(PluginError::Message(format!(
+        "All {NAME} mirrors failed: {}",
+        skipped.join(", ")
+    ))))
+}
+
+fn fetch_release_dirs(
+    channel: DartChannel,
+    env: &HostEnvironment,
+    config: &DartPluginConfig,
+) -> FnResult<Vec<String>> {
+    let url = format_url(&config.versions_url, &get_url_values(channel, env), config);
+    let separator = if url.contains('?') { '&' } else { '?' };
+
+    // Listings are paginated, follow "nextPageToken" until the last page
+    let prefixes = DartPrefixes::collect_pages(|page_token| match page_token {
+        Some(token) => fetch_json::<String, DartPrefixes>(format!(
+            "{url}{separator}pageToken={}",
+            encode_query_value(token)
+        )),
+        None => fetch_json::<&str, DartPrefixes>(&url),
+    })?;
+
+    // Prefixes are GCS paths like "channels/stable/release/3.7.1/"
+    Ok(prefixes
+        .iter()
+        .map(|item| {
+            item.trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or("")
+                .to_owned()
+        })
+        .collect())
+}
+
+fn fetch_version_file(
+    channel: DartChannel,
+    release_dir: &str,
+    env: &HostEnvironment,
+    config: &DartPluginConfig,
+) -> FnResult<DartLatest> {
+    let mut values = get_url_values(channel, env);
+    values.insert("version", release_dir.to_owned());
+
+    Ok(fetch_json::<String, DartLatest>(format_url(
+        &config.version_file_url,
+        &values,
+        config,
+    ))?)
+}
+
+fn get_cached_revisions(channel: DartChannel) -> FnResult<BTreeMap<String, String>> {
+    Ok(
+        var::get::<Json<BTreeMap<String, String>>>(format!("{}_revisions", channel.as_str()))?
+            .map(|Json(revisions)| revisions)
+            .unwrap_or_default(),
+    )
+}
+
+// Maps the revision directories of a channel to their versions. A channel has
+// hundreds of them, so their VERSION files are only fetched once per instance
+fn resolve_revisions(
+    channel: DartChannel,
+    release_dirs: &[String],
+    env: &HostEnvironment,
+    config: &DartPluginConfig,
+) -> FnResult<BTreeMap<String, String>> {
+    let mut revisions = get_cached_revisions(channel)?;
+    let mut resolved = false;
+
+    for release_dir in release_dirs {
+        if !is_revision(release_dir) || revisions.contains_key(release_dir) {
+            continue;
+        }
+
+        // A single broken release shouldn't prevent listing all the others,
+        // it's left out of the listing and retried on the next load
+        match fetch_version_file(channel, release_dir, env, config) {
+            Ok(file) => {
+                revisions.insert(release_dir.to_owned(), file.version);
+                resolved = true;
+            }
+            Err(error) => {
+                host_log!(
+                    stderr,
+                    "Skipping {NAME} release {release_dir} in the {} channel, unable to resolve its version: {}",
+                    channel.as_str(),
+                    error.0
+                );
+            }
+        };
+    }
+
+    if resolved {
+        var::set(
+            format!("{}_revisions", channel.as_str()),
+            Json(revisions.clone()),
+        )?;
+    }
+
+    Ok(revisions)
+}
+
+fn is_revision(release_dir: &str) -> bool {
+    !release_dir.is_empty() && release_dir.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+// Finds the directory a Dart 1.x release was archived under, which
+// is either its version or the SVN revision it was built from
+fn resolve_release_dir(
+    channel: DartChannel,
+    version: &Version,
+    env: &HostEnvironment,
+    config: &DartPluginConfig,
+) -> FnResult<String> {
+    let version = version.to_string();
+    let find_revision = |revisions: BTreeMap<String, String>| {
+        revisions
+            .into_iter()
+            .find_map(|(release_dir, release)| (release == version).then_some(release_dir))
+    };
+
+    // Known when the versions were loaded by this instance
+    if let Some(release_dir) = find_revision(get_cached_revisions(channel)?) {
+        return Ok(release_dir);
+    }
+
+    if fetch_version_file(channel, &version, env, config).is_ok() {
+        return Ok(version);
+    }
+
+    let release_dirs = fetch_release_dirs(channel, env, config)?;
+
+    if let Some(release_dir) =
+        find_revision(resolve_revisions(channel, &release_dirs, env, config)?)
+    {
+        return Ok(release_dir);
+    }
+
+    Err(plugin_err!(PluginError::Message(format!(
+        "Unable to find {NAME}@{version} in the {} channel of the archive",
+        channel.as_str()
+    ))))
+}
+
+fn get_dart_platform(os: &HostOS) -> Option<&'static str> {
+    match os {
+        HostOS::Linux => Some("linux"),
+        HostOS::MacOS => Some("macos"),
+        HostOS::Windows => Some("windows"),
+        _ => None,
+    }
+}
+
+fn get_dart_arch(arch: &HostArch) -> Option<&'static str> {
+    match arch {
+        HostArch::Riscv64 => Some("riscv64"),
+        HostArch::X86 => Some("ia32"),
+        HostArch::X64 => Some("x64"),
+        HostArch::Arm => Some("arm"),
+        HostArch::Arm64 => Some("arm64"),
+        _ => None,
+    }
+}
+
+fn get_go_arch(arch: &HostArch) -> Option<&'static str> {
+    match arch {
+        HostArch::Riscv64 => Some("riscv64"),
+        HostArch::X86 => Some("386"),
+        HostArch::X64 => Some("amd64"),
+        HostArch::Arm => Some("arm"),
+        HostArch::Arm64 => Some("arm64"),
+        _ => None,
+    }
+}
+
+// Version specific placeholders are added by the caller when known
+fn get_url_values(channel: DartChannel, env: &HostEnvironment) -> HashMap<&'static str, String> {
+    let mut values = HashMap::from_iter([
+        ("channel", channel.as_str().to_owned()),
+        ("os", env.os.to_string()),
+        ("rust_arch", env.arch.to_rust_arch()),
+        ("ext", "zip".to_owned()),
+    ]);
+
+    if let Some(platform) = get_dart_platform(&env.os) {
+        values.insert("platform", platform.to_owned());
+    }
+
+    if let Some(arch) = get_dart_arch(&env.arch) {
+        values.insert("arch", arch.to_owned());
+    }
+
+    if let Some(arch) = get_go_arch(&env.arch) {
+        values.insert("go_arch", arch.to_owned());
+    }
+
+    values
+}
+
+fn format_url(template: &str, values: &HashMap<&str, String>, config: &DartPluginConfig) -> String {
+    render_template(template, values, &config.placeholders)
+}
+
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+/// Replaces `{name}` placeholders in a URL template with their values. A value can be
+/// remapped per placeholder (e.g. `platform` `macos` to `darwin`) for mirrors with a
+/// different layout. Placeholders without a value are left untouched.
+pub fn render_template(
+    template: &str,
+    values: &HashMap<&str, String>,
+    mappings: &HashMap<String, HashMap<String, String>>,
+) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let name = &rest[1..end];
+
+        match values.get(name) {
+            Some(value) => output.push_str(
+                mappings
+                    .get(name)
+                    .and_then(|mapping| mapping.get(value))
+                    .unwrap_or(value),
+            ),
+            None => output.push_str(&rest[..=end]),
+        };
+
+        rest = &rest[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> HashMap<&'static str, String> {
+        HashMap::from([
+            ("channel", "stable".into()),
+            ("version", "3.7.1".into()),
+            ("major", "3".into()),
+            ("minor", "7".into()),
+            ("platform", "macos".into()),
+            ("arch", "x64".into()),
+            ("ext", "zip".into()),
+        ])
+    }
+
+    #[test]
+    fn replaces_placeholders() {
+        assert_eq!(
+            render_template(
+                "https://example.com/{channel}/{major}.{minor}/{version}/dartsdk-{platform}-{arch}-release.{ext}",
+                &values(),
+                &HashMap::new()
+            ),
+            "https://example.com/stable/3.7/3.7.1/dartsdk-macos-x64-release.zip"
+        );
+    }
+
+    #[test]
+    fn applies_value_mappings() {
+        let mappings = HashMap::from([
+            (
+                "platform".to_owned(),
+                HashMap::from([("macos".to_owned(), "darwin".to_owned())]),
+            ),
+            (
+                "arch".to_owned(),
+                HashMap::from([("x64".to_owned(), "amd64".to_owned())]),
+            ),
+            (
+                "ext".to_owned(),
+                HashMap::from([("zip".to_owned(), "tar.gz".to_owned())]),
+            ),
+        ]);
+
+        assert_eq!(
+            render_template("dart-{platform}-{arch}.{ext}", &values(), &mappings),
+            "dart-darwin-amd64.tar.gz"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        assert_eq!(
+            render_template("{channel}/{unknown}/{version", &values(), &HashMap::new()),
+            "stable/{unknown}/{version"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use proto_pdk_test_utils::*;
+
+mod dart_tool {
+    use super::*;
+
+    async fn build_instructions(os: HostOS, arch: HostArch) -> BuildInstructionsOutput {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(os, arch);
+            })
+            .await;
+
+        plugin
+            .tool
+            .plugin
+            .call_func_with(
+                "build_instructions",
+                BuildInstructionsInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("3.7.1").unwrap(),
+                        ..Default::default()
+                    },
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    fn get_command(instruction: &BuildInstruction) -> &CommandInstruction {
+        match instruction {
+            BuildInstruction::RunCommand(command) => command,
+            _ => panic!("Expected a command, got {instruction:?}"),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn builds_version_tag_with_gclient() {
+        let output = build_instructions(HostOS::Linux, HostArch::X64).await;
+
+        assert_eq!(output.instructions.len(), 6);
+
+        let BuildInstruction::InstallBuilder(builder) = &output.instructions[0] else {
+            panic!("Expected depot_tools to be installed first");
+        };
+
+        assert_eq!(builder.id, "depot_tools");
+        assert_eq!(builder.exe, std::path::PathBuf::from("gclient"));
+
+        let config = get_command(&output.instructions[1]);
+
+        assert!(config.builder);
+        assert_eq!(config.bin, "depot_tools");
+        assert_eq!(
+            config.args,
+            [
+                "config",
+                "--name=sdk",
+                "https://dart.googlesource.com/sdk.git"
+            ]
+        );
+
+        let sync = get_command(&output.instructions[2]);
+
+        assert_eq!(
+            sync.args,
+            ["sync", "--revision", "sdk@refs/tags/3.7.1", "--no-history"]
+        );
+        assert!(sync.env["PATH"].contains("depot_tools"));
+
+        let build = get_command(&output.instructions[3]);
+
+        assert!(!build.builder);
+        assert_eq!(build.bin, "python3");
+        assert_eq!(
+            build.args,
+            [
+                "tools/build.py",
+                "--mode",
+                "release",
+                "--arch",
+                "x64",
+                "create_sdk"
+            ]
+        );
+        assert_eq!(build.cwd, Some("sdk".into()));
+
+        assert_eq!(
+            output.instructions[4],
+            BuildInstruction::MoveFile("sdk/out/ReleaseX64/dart-sdk".into(), "dart-sdk".into())
+        );
+        assert_eq!(
+            output.instructions[5],
+            BuildInstruction::RemoveAllExcept(vec!["dart-sdk".into()])
+        );
+        assert!(output
+            .requirements
+            .contains(&BuildRequirement::CommandExistsOnPath("git".into())));
+        assert!(!output.system_dependencies.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn builds_into_xcodebuild_on_macos() {
+        let output = build_instructions(HostOS::MacOS, HostArch::Arm64).await;
+
+        assert_eq!(
+            output.instructions[4],
+            BuildInstruction::MoveFile(
+                "sdk/xcodebuild/ReleaseARM64/dart-sdk".into(),
+                "dart-sdk".into()
+            )
+        );
+        assert!(output
+            .requirements
+            .contains(&BuildRequirement::XcodeCommandLineTools));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn builds_on_unsupported_prebuilt_platforms() {
+        let output = build_instructions(HostOS::FreeBSD, HostArch::X64).await;
+
+        assert_eq!(
+            output.instructions[4],
+            BuildInstruction::MoveFile("sdk/out/ReleaseX64/dart-sdk".into(), "dart-sdk".into())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "the SDK build only supports the ia32, x64, arm, arm64 and riscv64")]
+    async fn fails_on_architectures_without_build_target() {
+        build_instructions(HostOS::Linux, HostArch::Powerpc64).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn uses_local_toolchain_on_windows() {
+        let output = build_instructions(HostOS::Windows, HostArch::X64).await;
+
+        let BuildInstruction::InstallBuilder(builder) = &output.instructions[0] else {
+            panic!("Expected depot_tools to be installed first");
+        };
+
+        assert_eq!(builder.exe, std::path::PathBuf::from("gclient.bat"));
+
+        let build = get_command(&output.instructions[3]);
+
+        assert_eq!(build.bin, "python");
+        assert_eq!(build.env["DEPOT_TOOLS_WIN_TOOLCHAIN"], "0");
+    }
+}
@@ -0,0 +1,412 @@
+mod utils;
+
+use proto_pdk_test_utils::*;
+use std::collections::HashMap;
+
+mod dart_tool {
+    use super::*;
+
+    // sha256 of "dart-sdk"
+    const HASH: &str = "60d075fb98315ee36552b73614733b54fb4d8c3e8a9ff6fa1252bf5f4b1a1c70";
+
+    fn create_input(sandbox: &ProtoWasmSandbox) -> VerifyChecksumInput {
+        VerifyChecksumInput {
+            checksum_file: VirtualPath::OnlyReal(
+                sandbox
+                    .path()
+                    .join("dartsdk-linux-x64-release.zip.sha256sum"),
+            ),
+            download_file: VirtualPath::OnlyReal(
+                sandbox.path().join("dartsdk-linux-x64-release.zip"),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn verifies_checksum() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file("dartsdk-linux-x64-release.zip", "dart-sdk");
+        sandbox.create_file(
+            "dartsdk-linux-x64-release.zip.sha256sum",
+            format!("{HASH} *dartsdk-linux-x64-release.zip\n"),
+        );
+
+        let plugin = sandbox.create_plugin("dart-test").await;
+
+        assert!(
+            plugin
+                .verify_checksum(create_input(&sandbox))
+                .await
+                .verified
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn verifies_checksum_with_multiple_entries() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file("dartsdk-linux-x64-release.zip", "dart-sdk");
+        sandbox.create_file(
+            "dartsdk-linux-x64-release.zip.sha256sum",
+            format!(
+                "{}  dartsdk-linux-arm64-release.zip\r\n{}  dartsdk-linux-x64-release.zip\r\n",
+                "0".repeat(64),
+                HASH.to_uppercase()
+            ),
+        );
+
+        let plugin = sandbox.create_plugin("dart-test").await;
+
+        assert!(
+            plugin
+                .verify_checksum(create_input(&sandbox))
+                .await
+                .verified
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(
+        expected = "expected sha256 0000000000000000000000000000000000000000000000000000000000000000 but got 60d075fb98315ee36552b73614733b54fb4d8c3e8a9ff6fa1252bf5f4b1a1c70"
+    )]
+    async fn fails_on_checksum_mismatch() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file("dartsdk-linux-x64-release.zip", "dart-sdk");
+        sandbox.create_file(
+            "dartsdk-linux-x64-release.zip.sha256sum",
+            format!("{} *dartsdk-linux-x64-release.zip\n", "0".repeat(64)),
+        );
+
+        let plugin = sandbox.create_plugin("dart-test").await;
+
+        plugin.verify_checksum(create_input(&sandbox)).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "Unable to find a checksum for dartsdk-linux-x64-release.zip")]
+    async fn fails_without_matching_entry() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file("dartsdk-linux-x64-release.zip", "dart-sdk");
+        sandbox.create_file(
+            "dartsdk-linux-x64-release.zip.sha256sum",
+            format!(
+                "{HASH}  dartsdk-macos-x64-release.zip\n{HASH}  dartsdk-macos-arm64-release.zip\n"
+            ),
+        );
+
+        let plugin = sandbox.create_plugin("dart-test").await;
+
+        plugin.verify_checksum(create_input(&sandbox)).await;
+    }
+
+    fn create_download_input() -> DownloadPrebuiltInput {
+        DownloadPrebuiltInput {
+            context: ToolContext {
+                version: VersionSpec::parse("3.7.1").unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn records_checksum_in_lockfile() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file(
+            ".prototools",
+            "[tools.dart-test]\nlockfile = \".dart-sdk.lock\"\n",
+        );
+        sandbox.create_file("dartsdk-linux-x64-release.zip", "dart-sdk");
+        sandbox.create_file(
+            "dartsdk-linux-x64-release.zip.sha256sum",
+            format!("{HASH} *dartsdk-linux-x64-release.zip\n"),
+        );
+
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([("lockfile", ".dart-sdk.lock")]));
+            })
+            .await;
+
+        plugin.download_prebuilt(create_download_input()).await;
+
+        assert!(
+            plugin
+                .verify_checksum(create_input(&sandbox))
+                .await
+                .verified
+        );
+
+        let lockfile: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(sandbox.path().join(".dart-sdk.lock")).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            lockfile,
+            serde_json::json!({
+                "3.7.1": {
+                    "linux-x64": {
+                        "url": "https://storage.googleapis.com/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip",
+                        "sha256": HASH,
+                    },
+                },
+            })
+        );
+
+        // Later installs use the recorded hash instead of the checksum file
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([("lockfile", ".dart-sdk.lock")]));
+            })
+            .await;
+        let output = plugin.download_prebuilt(create_download_input()).await;
+
+        assert_eq!(output.checksum, Some(HASH.into()));
+        assert_eq!(output.checksum_url, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "(from the lockfile .dart-sdk.lock)")]
+    async fn refuses_artifact_differing_from_lockfile() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file("dartsdk-linux-x64-release.zip", "tampered");
+        sandbox.create_file(
+            ".dart-sdk.lock",
+            serde_json::json!({
+                "3.7.1": {
+                    "linux-x64": {
+                        "url": "https://storage.googleapis.com/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip",
+                        "sha256": HASH,
+                    },
+                },
+            })
+            .to_string(),
+        );
+
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([("lockfile", ".dart-sdk.lock")]));
+            })
+            .await;
+        let output = plugin.download_prebuilt(create_download_input()).await;
+
+        // proto writes a provided checksum to a file before verifying
+        sandbox.create_file(
+            "dartsdk-linux-x64-release.zip.sha256sum",
+            output.checksum.unwrap(),
+        );
+
+        plugin.verify_checksum(create_input(&sandbox)).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "Lockfile .dart-sdk.lock not found in the current directory")]
+    async fn refuses_new_lockfile_outside_project_root() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([("lockfile", ".dart-sdk.lock")]));
+            })
+            .await;
+
+        plugin.download_prebuilt(create_download_input()).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "Lockfile /tmp/dart-sdk.lock must be relative to the project root")]
+    async fn refuses_absolute_lockfile_path() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([("lockfile", "/tmp/dart-sdk.lock")]));
+            })
+            .await;
+
+        plugin.download_prebuilt(create_download_input()).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "differs from the lockfile .dart-sdk.lock")]
+    async fn refuses_pinned_checksum_differing_from_lockfile() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file(
+            ".dart-sdk.lock",
+            serde_json::json!({
+                "3.7.1": {
+                    "linux-x64": {
+                        "url": "https://storage.googleapis.com/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip",
+                        "sha256": HASH,
+                    },
+                },
+            })
+            .to_string(),
+        );
+
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(serde_json::json!({
+                    "lockfile": ".dart-sdk.lock",
+                    "checksums": {
+                        "3.7.1": {
+                            "linux-x64": "0".repeat(64),
+                        },
+                    },
+                }));
+            })
+            .await;
+
+        plugin.download_prebuilt(create_download_input()).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "Unable to find a checksum for dartsdk-linux-x64-release.zip")]
+    async fn refuses_algorithm_prefix_from_checksum_url() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file("dartsdk-linux-x64-release.zip", "dart-sdk");
+
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([(
+                    "checksum-url",
+                    "https://checksums.example.com/dart/{version}/{platform}-{arch}.sha256",
+                )]));
+            })
+            .await;
+        plugin.download_prebuilt(create_download_input()).await;
+
+        // A mirror can't downgrade the hash by naming another algorithm
+        sandbox.create_file(
+            "dartsdk-linux-x64-release.zip.sha256sum",
+            "crc32c:00000000 *dartsdk-linux-x64-release.zip\n",
+        );
+
+        plugin.verify_checksum(create_input(&sandbox)).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn probes_mirrors_with_pinned_checksums_and_metadata_fallback() {
+        let broken_url = utils::serve_archive(HashMap::new());
+        let base_url = utils::serve_archive(HashMap::from([(
+            "/dart-archive/channels/stable/release/3.7.1/VERSION".into(),
+            r#"{"version": "3.7.1"}"#.into(),
+        )]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                builder.host(HostOS::Linux, HostArch::X64);
+                builder.tool_config(serde_json::json!({
+                    "mirrors": [broken_url, base_url],
+                    "metadata-fallback": true,
+                    "checksums": {
+                        "3.7.1": {
+                            "linux-x64": HASH,
+                        },
+                    },
+                }));
+            })
+            .await;
+        let output = plugin.download_prebuilt(create_download_input()).await;
+
+        assert_eq!(
+            output.download_url,
+            format!("{base_url}/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip")
+        );
+        assert_eq!(output.checksum.as_deref(), Some(HASH));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn skips_mirror_failing_checksum_request_with_metadata_fallback() {
+        // Serves the metadata, but fails on the checksum file
+        let broken_url = utils::serve_archive_with_status(
+            HashMap::from([(
+                "/storage/v1/b/dart-archive/o/channels%2Fstable%2Frelease%2F3.7.1%2Fsdk%2Fdartsdk-linux-x64-release.zip?alt=json".into(),
+                r#"{
+                    "name": "channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip",
+                    "md5Hash": "Mhr/TzBNKQuIeREqMtdiGg=="
+                }"#
+                .into(),
+            )]),
+            "503 Service Unavailable",
+        );
+        let base_url = utils::serve_archive(HashMap::from([(
+            "/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip.sha256sum"
+                .into(),
+            format!("{HASH} *dartsdk-linux-x64-release.zip\n"),
+        )]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                builder.host(HostOS::Linux, HostArch::X64);
+                builder.tool_config(serde_json::json!({
+                    "mirrors": [broken_url, base_url],
+                    "metadata-fallback": true,
+                }));
+            })
+            .await;
+        let output = plugin.download_prebuilt(create_download_input()).await;
+
+        // The SHA-256 file of the next mirror is used instead of the metadata hash
+        assert_eq!(output.checksum, None);
+        assert_eq!(
+            output.checksum_url,
+            Some(format!(
+                "{base_url}/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip.sha256sum"
+            ))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn falls_back_to_object_metadata() {
+        let base_url = utils::serve_archive(HashMap::from([(
+            "/storage/v1/b/dart-archive/o/channels%2Fstable%2Frelease%2F3.7.1%2Fsdk%2Fdartsdk-linux-x64-release.zip?alt=json".into(),
+            r#"{
+                "name": "channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip",
+                "md5Hash": "Mhr/TzBNKQuIeREqMtdiGg==",
+                "crc32c": "AAAAAA=="
+            }"#
+            .into(),
+        )]));
+
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file("dartsdk-linux-x64-release.zip", "dart-sdk");
+
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                builder.host(HostOS::Linux, HostArch::X64);
+                builder.tool_config(serde_json::json!({
+                    "mirrors": [base_url],
+                    "metadata-fallback": true,
+                }));
+            })
+            .await;
+        let output = plugin.download_prebuilt(create_download_input()).await;
+
+        assert_eq!(output.checksum_url, None);
+        assert_eq!(
+            output.checksum.as_deref(),
+            Some("md5:321aff4f304d290b8879112a32d7621a")
+        );
+
+        sandbox.create_file(
+            "dartsdk-linux-x64-release.zip.sha256sum",
+            output.checksum.unwrap(),
+        );
+
+        assert!(
+            plugin
+                .verify_checksum(create_input(&sandbox))
+                .await
+                .verified
+        );
+    }
+}
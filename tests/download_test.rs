@@ -1,4 +1,7 @@
+mod utils;
+
 use proto_pdk_test_utils::*;
+use std::collections::HashMap;
 
 mod dart_tool {
     use super::*;
@@ -34,6 +37,556 @@ mod dart_tool {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn falls_back_to_rosetta_for_old_versions() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::MacOS, HostArch::Arm64);
+                config.tool_config(HashMap::from([("rosetta-fallback", true)]));
+            })
+            .await;
+
+        assert_eq!(
+            plugin
+                .download_prebuilt(DownloadPrebuiltInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("2.12.0").unwrap(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .await
+                .download_url,
+            "https://storage.googleapis.com/dart-archive/channels/stable/release/2.12.0/sdk/dartsdk-macos-x64-release.zip"
+        );
+        assert_eq!(
+            plugin
+                .download_prebuilt(DownloadPrebuiltInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("2.14.1").unwrap(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .await
+                .download_url,
+            "https://storage.googleapis.com/dart-archive/channels/stable/release/2.14.1/sdk/dartsdk-macos-arm64-release.zip"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn falls_back_to_x64_on_windows_arm64() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Windows, HostArch::Arm64);
+                config.tool_config(HashMap::from([("windows-x64-fallback", true)]));
+            })
+            .await;
+
+        for (version, url) in [
+            (
+                "3.2.0",
+                "https://storage.googleapis.com/dart-archive/channels/stable/release/3.2.0/sdk/dartsdk-windows-x64-release.zip",
+            ),
+            (
+                "3.2.0-30.0.beta",
+                "https://storage.googleapis.com/dart-archive/channels/beta/release/3.2.0-30.0.beta/sdk/dartsdk-windows-x64-release.zip",
+            ),
+            (
+                "3.3.0",
+                "https://storage.googleapis.com/dart-archive/channels/stable/release/3.3.0/sdk/dartsdk-windows-arm64-release.zip",
+            ),
+        ] {
+            assert_eq!(
+                plugin
+                    .download_prebuilt(DownloadPrebuiltInput {
+                        context: ToolContext {
+                            version: VersionSpec::parse(version).unwrap(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .await
+                    .download_url,
+                url
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn supports_musl_with_gcompat() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host_environment(HostEnvironment {
+                    os: HostOS::Linux,
+                    arch: HostArch::X64,
+                    libc: HostLibc::Musl,
+                    ..Default::default()
+                });
+                config.tool_config(HashMap::from([("allow-musl", true)]));
+            })
+            .await;
+
+        assert_eq!(
+            plugin
+                .download_prebuilt(DownloadPrebuiltInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("3.7.1").unwrap(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .await
+                .download_url,
+            "https://storage.googleapis.com/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn supports_dev_channel() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+            })
+            .await;
+
+        assert_eq!(
+            plugin
+                .download_prebuilt(DownloadPrebuiltInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("2.19.0-444.1.dev").unwrap(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .await,
+            DownloadPrebuiltOutput {
+                download_url:
+                    "https://storage.googleapis.com/dart-archive/channels/dev/release/2.19.0-444.1.dev/sdk/dartsdk-linux-x64-release.zip"
+                        .into(),
+                checksum_url: Some("https://storage.googleapis.com/dart-archive/channels/dev/release/2.19.0-444.1.dev/sdk/dartsdk-linux-x64-release.zip.sha256sum".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn infers_channel_from_prerelease() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+            })
+            .await;
+
+        for (version, channel) in [
+            ("3.8.0-70.1.beta", "beta"),
+            ("3.9.0-0.0.dev", "dev"),
+            ("2.0.0-dev.69.5", "dev"),
+        ] {
+            assert_eq!(
+                plugin
+                    .download_prebuilt(DownloadPrebuiltInput {
+                        context: ToolContext {
+                            version: VersionSpec::parse(version).unwrap(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .await
+                    .download_url,
+                format!("https://storage.googleapis.com/dart-archive/channels/{channel}/release/{version}/sdk/dartsdk-linux-x64-release.zip")
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn supports_dist_url_placeholders() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::MacOS, HostArch::X64);
+                config.tool_config(HashMap::from([
+                    (
+                        "dist-url",
+                        serde_json::Value::from("https://mirror.example.com/dart/{major}.{minor}/{patch}-{prerelease}/{os}/dart-{platform}-{go_arch}-{rust_arch}.{ext}"),
+                    ),
+                    (
+                        "placeholders",
+                        serde_json::json!({
+                            "platform": { "macos": "darwin" },
+                            "ext": { "zip": "tar.gz" },
+                        }),
+                    ),
+                ]));
+            })
+            .await;
+
+        assert_eq!(
+            plugin
+                .download_prebuilt(DownloadPrebuiltInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("3.8.0-70.1.beta").unwrap(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .await
+                .download_url,
+            "https://mirror.example.com/dart/3.8/0-70.1.beta/macos/dart-darwin-amd64-x86_64.tar.gz"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn supports_dist_url_overrides() {
+        let sandbox = create_empty_proto_sandbox();
+        let config = serde_json::json!({
+            "dist-url-overrides": {
+                "linux-riscv64": "https://builds.example.com/dart/{version}/dartsdk-{platform}-{arch}.{ext}",
+            },
+        });
+        let plugin_riscv = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                builder.host(HostOS::Linux, HostArch::Riscv64);
+                builder.tool_config(&config);
+            })
+            .await;
+        let plugin_x64 = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                builder.host(HostOS::Linux, HostArch::X64);
+                builder.tool_config(&config);
+            })
+            .await;
+        let input = DownloadPrebuiltInput {
+            context: ToolContext {
+                version: VersionSpec::parse("3.7.1").unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            plugin_riscv
+                .download_prebuilt(input.clone())
+                .await
+                .download_url,
+            "https://builds.example.com/dart/3.7.1/dartsdk-linux-riscv64.zip"
+        );
+        assert_eq!(
+            plugin_x64.download_prebuilt(input).await.download_url,
+            "https://storage.googleapis.com/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn supports_checksum_url() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([(
+                    "checksum-url",
+                    "https://checksums.example.com/dart/{version}/{platform}-{arch}.sha256",
+                )]));
+            })
+            .await;
+
+        let output = plugin
+            .download_prebuilt(DownloadPrebuiltInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+
+        assert_eq!(
+            output.checksum_url,
+            Some("https://checksums.example.com/dart/3.7.1/linux-x64.sha256".into())
+        );
+        assert_eq!(output.checksum, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn supports_pinned_checksums() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                builder.host(HostOS::Linux, HostArch::X64);
+                builder.tool_config(serde_json::json!({
+                    "checksums": {
+                        "3.7.1": {
+                            "linux-x64": "60d075fb98315ee36552b73614733b54fb4d8c3e8a9ff6fa1252bf5f4b1a1c70",
+                        },
+                    },
+                }));
+            })
+            .await;
+
+        let output = plugin
+            .download_prebuilt(DownloadPrebuiltInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+
+        assert_eq!(output.checksum_url, None);
+        assert_eq!(
+            output.checksum,
+            Some("60d075fb98315ee36552b73614733b54fb4d8c3e8a9ff6fa1252bf5f4b1a1c70".into())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "No pinned checksum for Dart@3.7.1 on linux-arm64")]
+    async fn fails_without_pinned_checksum() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                builder.host(HostOS::Linux, HostArch::Arm64);
+                builder.tool_config(serde_json::json!({
+                    "checksums": {
+                        "3.7.1": {
+                            "linux-x64": "60d075fb98315ee36552b73614733b54fb4d8c3e8a9ff6fa1252bf5f4b1a1c70",
+                        },
+                    },
+                }));
+            })
+            .await;
+
+        plugin
+            .download_prebuilt(DownloadPrebuiltInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn supports_disabling_checksums() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([("verify-checksums", false)]));
+            })
+            .await;
+
+        let output = plugin
+            .download_prebuilt(DownloadPrebuiltInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+
+        assert_eq!(output.checksum_url, None);
+        assert_eq!(output.checksum, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn falls_back_to_next_mirror() {
+        let broken_url = utils::serve_archive(HashMap::new());
+        let base_url = utils::serve_archive(HashMap::from([(
+            "/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip.sha256sum"
+                .into(),
+            "e4ae1f9ab4ab2b13bd0e4e5e6a4b7b3c  *dartsdk-linux-x64-release.zip".into(),
+        )]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([(
+                    "mirrors",
+                    vec![broken_url.clone(), base_url.clone()],
+                )]));
+            })
+            .await;
+
+        assert_eq!(
+            plugin
+                .download_prebuilt(DownloadPrebuiltInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("3.7.1").unwrap(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .await,
+            DownloadPrebuiltOutput {
+                download_url: format!(
+                    "{base_url}/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip"
+                ),
+                checksum_url: Some(format!(
+                    "{base_url}/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip.sha256sum"
+                )),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reports_errors_unrelated_to_mirrors_once() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([(
+                    "mirrors",
+                    vec![
+                        utils::serve_archive(HashMap::new()),
+                        utils::serve_archive(HashMap::new()),
+                    ],
+                )]));
+            })
+            .await;
+
+        let error = plugin
+            .tool
+            .plugin
+            .call_func_with::<_, DownloadPrebuiltOutput>(
+                "download_prebuilt",
+                DownloadPrebuiltInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("3.7.1-rc.1").unwrap(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("Unable to determine the release channel of Dart@3.7.1-rc.1"));
+        assert!(!error.contains("mirrors failed"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn uses_revision_dir_for_legacy_releases() {
+        let base_url = utils::serve_archive(HashMap::from([
+            (
+                "/o?prefix=stable".into(),
+                r#"{"prefixes": ["channels/stable/release/44672/", "channels/stable/release/45396/"]}"#.into(),
+            ),
+            (
+                "/stable/44672/VERSION".into(),
+                r#"{"revision": "44672", "version": "1.9.1"}"#.into(),
+            ),
+            (
+                "/stable/45396/VERSION".into(),
+                r#"{"revision": "45396", "version": "1.9.3"}"#.into(),
+            ),
+        ]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([
+                    ("versions-url", format!("{base_url}/o?prefix={{channel}}")),
+                    (
+                        "version-file-url",
+                        format!("{base_url}/{{channel}}/{{version}}/VERSION"),
+                    ),
+                ]));
+            })
+            .await;
+
+        assert_eq!(
+            plugin
+                .download_prebuilt(DownloadPrebuiltInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("1.9.3").unwrap(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .await
+                .download_url,
+            "https://storage.googleapis.com/dart-archive/channels/stable/release/45396/sdk/dartsdk-linux-x64-release.zip"
+        );
+    }
+
+    // Downloads the canary build, whose version is read from the served VERSION file
+    async fn download_canary(arch: HostArch, version_file: &str) -> DownloadPrebuiltOutput {
+        let base_url = utils::serve_archive(HashMap::from([(
+            "/main/raw/latest/VERSION".into(),
+            version_file.into(),
+        )]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, arch);
+                config.tool_config(HashMap::from([
+                    (
+                        "canary-latest-url",
+                        format!("{base_url}/{{channel}}/raw/latest/VERSION"),
+                    ),
+                    (
+                        "canary-dist-url",
+                        format!(
+                            "{base_url}/{{channel}}/{{version}}/dartsdk-{{platform}}-{{arch}}.zip"
+                        ),
+                    ),
+                ]));
+            })
+            .await;
+
+        plugin
+            .download_prebuilt(DownloadPrebuiltInput {
+                context: ToolContext {
+                    version: VersionSpec::Canary,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn supports_canary() {
+        let output = download_canary(HostArch::X64, r#"{"version": "3.9.0-100.0.dev"}"#).await;
+
+        assert!(output
+            .download_url
+            .ends_with("/main/3.9.0-100.0.dev/dartsdk-linux-x64.zip"));
+        assert_eq!(
+            output.checksum_url,
+            Some(format!("{}.sha256sum", output.download_url))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "Unable to parse Dart canary version latest")]
+    async fn fails_on_unparsable_canary_version() {
+        download_canary(HostArch::X64, r#"{"version": "latest"}"#).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(
+        expected = "Unable to install Dart@3.9.0-100.0.dev for the current architecture"
+    )]
+    async fn fails_on_canary_version_unsupported_by_host() {
+        // Linux ia32 builds were dropped in 3.8.0
+        download_canary(HostArch::X86, r#"{"version": "3.9.0-100.0.dev"}"#).await;
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn locates_unix_bin() {
         let sandbox = create_empty_proto_sandbox();
@@ -115,4 +668,239 @@ mod dart_tool {
             Some("dart-sdk/bin/dartaotruntime.exe".into())
         );
     }
+
+    async fn locate_exes(os: HostOS, version: &str) -> Vec<(String, String)> {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(os, HostArch::X64);
+            })
+            .await;
+
+        let mut exes = plugin
+            .locate_executables(LocateExecutablesInput {
+                context: ToolContext {
+                    version: VersionSpec::parse(version).unwrap(),
+                    ..Default::default()
+                },
+            })
+            .await
+            .exes
+            .into_iter()
+            .map(|(name, config)| {
+                (
+                    name,
+                    config.exe_path.unwrap().to_string_lossy().into_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        exes.sort();
+        exes
+    }
+
+    fn exes(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter()
+            .map(|(name, path)| (name.to_string(), path.to_string()))
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn locates_legacy_tools_on_unix() {
+        for os in [HostOS::Linux, HostOS::MacOS] {
+            assert_eq!(
+                locate_exes(os, "1.24.3").await,
+                exes(&[
+                    ("dart", "dart-sdk/bin/dart"),
+                    ("dart2js", "dart-sdk/bin/dart2js"),
+                    ("dartanalyzer", "dart-sdk/bin/dartanalyzer"),
+                    ("dartdevc", "dart-sdk/bin/dartdevc"),
+                    ("dartfmt", "dart-sdk/bin/dartfmt"),
+                    ("pub", "dart-sdk/bin/pub"),
+                ])
+            );
+            assert_eq!(
+                locate_exes(os, "1.23.0").await,
+                exes(&[
+                    ("dart", "dart-sdk/bin/dart"),
+                    ("dart2js", "dart-sdk/bin/dart2js"),
+                    ("dartanalyzer", "dart-sdk/bin/dartanalyzer"),
+                    ("dartfmt", "dart-sdk/bin/dartfmt"),
+                    ("pub", "dart-sdk/bin/pub"),
+                ])
+            );
+            assert_eq!(
+                locate_exes(os, "2.14.4").await,
+                exes(&[
+                    ("dart", "dart-sdk/bin/dart"),
+                    ("dart2js", "dart-sdk/bin/dart2js"),
+                    ("dartanalyzer", "dart-sdk/bin/dartanalyzer"),
+                    ("dartaotruntime", "dart-sdk/bin/dartaotruntime"),
+                    ("dartdevc", "dart-sdk/bin/dartdevc"),
+                    ("dartfmt", "dart-sdk/bin/dartfmt"),
+                    ("pub", "dart-sdk/bin/pub"),
+                ])
+            );
+            assert_eq!(
+                locate_exes(os, "2.17.7").await,
+                exes(&[
+                    ("dart", "dart-sdk/bin/dart"),
+                    ("dart2js", "dart-sdk/bin/dart2js"),
+                    ("dartanalyzer", "dart-sdk/bin/dartanalyzer"),
+                    ("dartaotruntime", "dart-sdk/bin/dartaotruntime"),
+                    ("dartdevc", "dart-sdk/bin/dartdevc"),
+                ])
+            );
+            assert_eq!(
+                locate_exes(os, "2.18.0").await,
+                exes(&[
+                    ("dart", "dart-sdk/bin/dart"),
+                    ("dartaotruntime", "dart-sdk/bin/dartaotruntime"),
+                ])
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn locates_legacy_tools_on_windows() {
+        assert_eq!(
+            locate_exes(HostOS::Windows, "2.5.2").await,
+            exes(&[
+                ("dart", "dart-sdk/bin/dart.exe"),
+                ("dart2js", "dart-sdk/bin/dart2js.bat"),
+                ("dartanalyzer", "dart-sdk/bin/dartanalyzer.bat"),
+                ("dartdevc", "dart-sdk/bin/dartdevc.bat"),
+                ("dartfmt", "dart-sdk/bin/dartfmt.bat"),
+                ("pub", "dart-sdk/bin/pub.bat"),
+            ])
+        );
+        assert_eq!(
+            locate_exes(HostOS::Windows, "2.16.2").await,
+            exes(&[
+                ("dart", "dart-sdk/bin/dart.exe"),
+                ("dart2js", "dart-sdk/bin/dart2js.bat"),
+                ("dartanalyzer", "dart-sdk/bin/dartanalyzer.bat"),
+                ("dartaotruntime", "dart-sdk/bin/dartaotruntime.exe"),
+                ("dartdevc", "dart-sdk/bin/dartdevc.bat"),
+                ("pub", "dart-sdk/bin/pub.bat"),
+            ])
+        );
+        assert_eq!(
+            locate_exes(HostOS::Windows, "3.7.1").await,
+            exes(&[
+                ("dart", "dart-sdk/bin/dart.exe"),
+                ("dartaotruntime", "dart-sdk/bin/dartaotruntime.exe"),
+            ])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn locates_latest_tools_for_canary() {
+        assert_eq!(
+            locate_exes(HostOS::Linux, "canary").await,
+            exes(&[
+                ("dart", "dart-sdk/bin/dart"),
+                ("dartaotruntime", "dart-sdk/bin/dartaotruntime"),
+            ])
+        );
+    }
+
+    async fn locate_compat_shims(version: &str) -> HashMap<String, ExecutableConfig> {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([("compat-shims", true)]));
+            })
+            .await;
+
+        plugin
+            .locate_executables(LocateExecutablesInput {
+                context: ToolContext {
+                    version: VersionSpec::parse(version).unwrap(),
+                    ..Default::default()
+                },
+            })
+            .await
+            .exes
+            .into_iter()
+            .filter(|(_, config)| config.exe_path.is_none())
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn forwards_removed_tools_to_dart_subcommands() {
+        let shims = locate_compat_shims("3.7.1").await;
+
+        assert_eq!(shims.len(), 3);
+
+        for (name, command) in [
+            ("dartanalyzer", "analyze"),
+            ("dartfmt", "format"),
+            ("pub", "pub"),
+        ] {
+            let shim = shims.get(name).unwrap();
+
+            assert_eq!(shim.parent_exe_name.as_deref(), Some("dart"));
+            assert_eq!(
+                shim.shim_before_args,
+                Some(StringOrVec::String(command.into()))
+            );
+            assert!(!shim.primary);
+        }
+
+        // Only the tools that are missing from the SDK are forwarded
+        assert_eq!(
+            locate_compat_shims("2.16.2")
+                .await
+                .into_keys()
+                .collect::<Vec<_>>(),
+            vec!["dartfmt".to_owned()]
+        );
+
+        // Before the subcommands existed
+        assert!(locate_compat_shims("2.9.3").await.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn doesnt_forward_removed_tools_by_default() {
+        assert_eq!(
+            locate_exes(HostOS::Linux, "3.7.1")
+                .await
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            vec!["dart", "dartaotruntime"]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn locates_sdk_bin_dirs() {
+        for os in [HostOS::Linux, HostOS::MacOS, HostOS::Windows] {
+            let sandbox = create_empty_proto_sandbox();
+            let plugin = sandbox
+                .create_plugin_with_config("dart-test", |config| {
+                    config.host(os, HostArch::X64);
+                })
+                .await;
+
+            let output = plugin
+                .locate_executables(LocateExecutablesInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse("3.7.1").unwrap(),
+                        ..Default::default()
+                    },
+                })
+                .await;
+
+            assert_eq!(
+                output.exes_dirs,
+                vec![
+                    std::path::PathBuf::from("dart-sdk/bin"),
+                    std::path::PathBuf::from("dart-sdk/bin/utils")
+                ]
+            );
+            assert!(output.exes.get("dart").unwrap().primary);
+        }
+    }
 }
@@ -0,0 +1,84 @@
+mod utils;
+
+use proto_pdk_test_utils::*;
+use std::collections::HashMap;
+use std::env;
+
+mod dart_tool {
+    use super::*;
+
+    // Environment variables are shared by the whole test process,
+    // so every scenario runs sequentially within a single test
+    #[tokio::test(flavor = "multi_thread")]
+    async fn uses_mirror_environment_variables() {
+        let routes = utils::mirror_routes(&[
+            ("stable", "3.7.1", &["3.7.1"]),
+            ("beta", "3.8.0-70.1.beta", &["3.8.0-70.1.beta"]),
+            ("dev", "2.19.0-444.1.dev", &["2.19.0-444.1.dev"]),
+        ]);
+
+        let base_url = utils::serve_archive(routes);
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([(
+                    "mirrors",
+                    vec!["https://dart-mirror.example.com"],
+                )]));
+            })
+            .await;
+        let input = DownloadPrebuiltInput {
+            context: ToolContext {
+                version: VersionSpec::parse("3.7.1").unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        env::set_var("FLUTTER_STORAGE_BASE_URL", &base_url);
+
+        assert_eq!(
+            plugin
+                .load_versions(LoadVersionsInput::default())
+                .await
+                .latest,
+            Some(UnresolvedVersionSpec::parse("3.7.1").unwrap())
+        );
+        assert_eq!(
+            plugin.download_prebuilt(input.clone()).await.download_url,
+            format!("{base_url}/dart-archive/channels/stable/release/3.7.1/sdk/dartsdk-linux-x64-release.zip")
+        );
+
+        env::set_var(
+            "PROTO_DART_DIST_URL",
+            "https://dist.example.com/{version}/dartsdk-{platform}-{arch}.zip",
+        );
+
+        assert_eq!(
+            plugin.download_prebuilt(input.clone()).await.download_url,
+            "https://dist.example.com/3.7.1/dartsdk-linux-x64.zip"
+        );
+
+        // Without the Flutter variable, the configured mirrors are replaced too
+        env::remove_var("FLUTTER_STORAGE_BASE_URL");
+        env::set_var(
+            "PROTO_DART_DIST_URL",
+            format!("{base_url}/{{version}}/dartsdk-{{platform}}-{{arch}}.zip"),
+        );
+
+        assert_eq!(
+            plugin
+                .load_versions(LoadVersionsInput::default())
+                .await
+                .latest,
+            Some(UnresolvedVersionSpec::parse("3.7.1").unwrap())
+        );
+        assert_eq!(
+            plugin.download_prebuilt(input).await.download_url,
+            format!("{base_url}/3.7.1/dartsdk-linux-x64.zip")
+        );
+
+        env::remove_var("PROTO_DART_DIST_URL");
+    }
+}
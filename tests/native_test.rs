@@ -0,0 +1,266 @@
+mod utils;
+
+use proto_pdk_test_utils::*;
+use std::collections::HashMap;
+
+mod dart_tool {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn skips_native_install_by_default() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox.create_plugin("dart-test").await;
+
+        let output = plugin
+            .native_install(NativeInstallInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+
+        assert!(output.skip_install);
+        assert!(!output.installed);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn requires_a_version_for_native_install() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.tool_config(HashMap::from([("native-install", true)]));
+            })
+            .await;
+
+        let output = plugin
+            .native_install(NativeInstallInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("latest").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+
+        assert!(!output.installed);
+        assert!(output.error.unwrap().contains("not latest"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn skips_native_uninstall_for_archive_installs() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox.create_plugin("dart-test").await;
+
+        let output = plugin
+            .native_uninstall(NativeUninstallInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    ..Default::default()
+                },
+            })
+            .await;
+
+        assert!(output.skip_uninstall);
+        assert!(!output.uninstalled);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn keeps_native_package_linked_by_other_versions() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file(
+            ".proto/tools/dart-test/3.7.1/.native-package.json",
+            r#"{"manager": "apt", "name": "dart", "version": "3.7.1-1"}"#,
+        );
+        sandbox.create_file(
+            ".proto/tools/dart-test/3.7.0/.native-package.json",
+            r#"{"manager": "apt", "name": "dart", "version": "3.7.0-1"}"#,
+        );
+
+        let plugin = sandbox.create_plugin("dart-test").await;
+
+        let output = plugin
+            .native_uninstall(NativeUninstallInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    tool_dir: plugin
+                        .tool
+                        .to_virtual_path(&sandbox.path().join(".proto/tools/dart-test/3.7.1")),
+                    ..Default::default()
+                },
+            })
+            .await;
+
+        assert!(output.skip_uninstall);
+        assert!(!output.uninstalled);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn links_sdk_bundled_with_flutter() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file(
+            ".proto/tools/flutter/3.24.3/bin/cache/dart-sdk/version",
+            "3.5.3\n",
+        );
+        sandbox.create_file(
+            ".proto/tools/flutter/3.27.4/flutter/bin/cache/dart-sdk/version",
+            "3.6.2\n",
+        );
+
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.tool_config(HashMap::from([("flutter-sdk", true)]));
+            })
+            .await;
+
+        for (version, flutter_dir) in [("3.5.3", "3.24.3"), ("3.6.2", "3.27.4/flutter")] {
+            let output = plugin
+                .native_install(NativeInstallInput {
+                    context: ToolContext {
+                        version: VersionSpec::parse(version).unwrap(),
+                        ..Default::default()
+                    },
+                    install_dir: plugin.tool.to_virtual_path(&sandbox.path().join(version)),
+                })
+                .await;
+
+            assert!(output.installed);
+            assert_eq!(
+                std::fs::canonicalize(sandbox.path().join(version).join("dart-sdk")).unwrap(),
+                std::fs::canonicalize(
+                    sandbox
+                        .path()
+                        .join(".proto/tools/flutter")
+                        .join(flutter_dir)
+                        .join("bin/cache/dart-sdk")
+                )
+                .unwrap()
+            );
+        }
+
+        let output = plugin
+            .native_install(NativeInstallInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+
+        assert!(output.skip_install);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn skips_flutter_sdk_by_default() {
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file(
+            ".proto/tools/flutter/3.24.3/bin/cache/dart-sdk/version",
+            "3.5.3",
+        );
+
+        let plugin = sandbox.create_plugin("dart-test").await;
+
+        let output = plugin
+            .native_install(NativeInstallInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.5.3").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+
+        assert!(output.skip_install);
+    }
+
+    // A single test, as DART_SDK and PATH are read from the environment of the test process
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn links_system_sdk_from_environment() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let base_url = utils::serve_archive(utils::channel_routes(&[
+            ("stable", "3.7.1", &["3.7.1"]),
+            ("beta", "3.8.0-70.1.beta", &[]),
+            ("dev", "2.19.0-444.1.dev", &[]),
+        ]));
+
+        let sandbox = create_empty_proto_sandbox();
+        sandbox.create_file(
+            "system sdk/dart-sdk/bin/dart",
+            "#!/bin/sh\necho 'Dart SDK version: 3.6.2 (stable) (Wed Jan 29 01:20:39 2025 -0800) on \"linux_x64\"'\n",
+        );
+
+        let sdk_dir = sandbox.path().join("system sdk/dart-sdk");
+        let bin = sdk_dir.join("bin/dart");
+        std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(serde_json::json!({
+                    "versions-url": format!("{base_url}/o?prefix={{channel}}"),
+                    "latest-url": format!("{base_url}/{{channel}}/VERSION"),
+                    "system-sdk": true,
+                }));
+            })
+            .await;
+
+        std::env::set_var("DART_SDK", &sdk_dir);
+
+        let versions = plugin.load_versions(LoadVersionsInput::default()).await;
+
+        let install = |version: &str| {
+            plugin.native_install(NativeInstallInput {
+                context: ToolContext {
+                    version: VersionSpec::parse(version).unwrap(),
+                    ..Default::default()
+                },
+                install_dir: plugin.tool.to_virtual_path(&sandbox.path().join("install")),
+            })
+        };
+
+        let other = install("3.7.1").await;
+        let system = install("3.6.2").await;
+
+        std::env::remove_var("DART_SDK");
+
+        assert_eq!(
+            versions.aliases.get("system"),
+            Some(&UnresolvedVersionSpec::parse("3.6.2").unwrap())
+        );
+        assert!(versions
+            .versions
+            .contains(&VersionSpec::parse("3.6.2").unwrap()));
+
+        assert!(other.skip_install);
+        assert!(system.installed);
+        assert_eq!(
+            std::fs::read_link(sandbox.path().join("install/dart-sdk")).unwrap(),
+            sdk_dir
+        );
+
+        // Without DART_SDK, the symlinked dart on PATH is followed to its SDK
+        let path_dir = sandbox.path().join("path bin");
+        std::fs::create_dir_all(&path_dir).unwrap();
+        std::os::unix::fs::symlink(&bin, path_dir.join("dart")).unwrap();
+
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        std::env::set_var(
+            "PATH",
+            std::env::join_paths(std::iter::once(path_dir).chain(std::env::split_paths(&path)))
+                .unwrap(),
+        );
+
+        let versions = plugin.load_versions(LoadVersionsInput::default()).await;
+
+        std::env::set_var("PATH", path);
+
+        assert_eq!(
+            versions.aliases.get("system"),
+            Some(&UnresolvedVersionSpec::parse("3.6.2").unwrap())
+        );
+    }
+}
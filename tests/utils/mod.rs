@@ -0,0 +1,126 @@
+// Each test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// A channel with its latest version and the release directories in its listing
+pub type Channel<'a> = (&'a str, &'a str, &'a [&'a str]);
+
+// Routes for stand-in channels, configured through `latest-url = {base_url}/{channel}/VERSION`
+// and `versions-url = {base_url}/o?prefix={channel}`.
+pub fn channel_routes(channels: &[Channel]) -> HashMap<String, String> {
+    channels
+        .iter()
+        .flat_map(|(channel, version, releases)| {
+            [
+                (format!("/{channel}/VERSION"), version_body(version)),
+                (
+                    format!("/o?prefix={channel}"),
+                    listing_body(channel, releases),
+                ),
+            ]
+        })
+        .collect()
+}
+
+// Routes for stand-in channels at the Dart archive and GCS paths, for use as a mirror.
+pub fn mirror_routes(channels: &[Channel]) -> HashMap<String, String> {
+    channels
+        .iter()
+        .flat_map(|(channel, version, releases)| {
+            [
+                (
+                    format!("/dart-archive/channels/{channel}/release/latest/VERSION"),
+                    version_body(version),
+                ),
+                (
+                    format!("/storage/v1/b/dart-archive/o?delimiter=%2F&prefix=channels%2F{channel}%2Frelease%2F&alt=json"),
+                    listing_body(channel, releases),
+                ),
+            ]
+        })
+        .collect()
+}
+
+fn version_body(version: &str) -> String {
+    format!(r#"{{"version": "{version}"}}"#)
+}
+
+fn listing_body(channel: &str, releases: &[&str]) -> String {
+    let prefixes = releases
+        .iter()
+        .map(|release| format!(r#""channels/{channel}/release/{release}/""#))
+        .collect::<Vec<_>>();
+
+    format!(r#"{{"prefixes": [{}]}}"#, prefixes.join(", "))
+}
+
+// Serves canned responses for request paths (including the query string),
+// standing in for the Dart archive and its GCS listing API.
+pub fn serve_archive(routes: HashMap<String, String>) -> String {
+    serve_archive_with_log(routes).0
+}
+
+// Like serve_archive, but also records the path of every request.
+pub fn serve_archive_with_log(
+    routes: HashMap<String, String>,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    serve(routes, "404 Not Found")
+}
+
+// Like serve_archive, but answers unknown paths with the given status instead of a 404.
+pub fn serve_archive_with_status(routes: HashMap<String, String>, status: &'static str) -> String {
+    serve(routes, status).0
+}
+
+fn serve(
+    routes: HashMap<String, String>,
+    status: &'static str,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    let requests = Arc::new(Mutex::new(vec![]));
+    let log = Arc::clone(&requests);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+
+            // Drain the request headers
+            let mut header = String::new();
+
+            while reader.read_line(&mut header).is_ok_and(|size| size > 2) {
+                header.clear();
+            }
+
+            let path = request_line.split(' ').nth(1).unwrap_or("/");
+            log.lock().unwrap().push(path.to_owned());
+
+            let response = match routes.get(path) {
+                Some(body) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                ),
+                None => {
+                    format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                }
+            };
+
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    (base_url, requests)
+}
@@ -1,4 +1,7 @@
+mod utils;
+
 use proto_pdk_test_utils::*;
+use std::collections::HashMap;
 
 mod dart_tool {
     use super::*;
@@ -14,7 +17,7 @@ mod dart_tool {
     }
 
     #[tokio::test(flavor = "multi_thread")]
-    async fn sets_latest_stable_beta_alias() {
+    async fn sets_latest_stable_beta_dev_alias() {
         let sandbox = create_empty_proto_sandbox();
         let plugin = sandbox.create_plugin("dart-test").await;
 
@@ -28,6 +31,454 @@ mod dart_tool {
         assert_eq!(output.aliases.get("stable"), output.latest.as_ref());
 
         assert!(output.aliases.contains_key("beta"));
+        assert!(output.aliases.contains_key("dev"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn loads_versions_from_configured_urls() {
+        let mut routes = utils::channel_routes(&[
+            ("stable", "3.7.1", &[]),
+            ("beta", "3.8.0-70.1.beta", &["3.8.0-70.1.beta"]),
+            ("dev", "2.19.0-444.1.dev", &["2.19.0-444.1.dev"]),
+        ]);
+        routes.extend([
+            (
+                "/o?prefix=stable".into(),
+                r#"{"prefixes": ["channels/stable/release/3.7.0/"], "nextPageToken": "stable/2"}"#.into(),
+            ),
+            (
+                "/o?prefix=stable&pageToken=stable%2F2".into(),
+                r#"{"prefixes": ["channels/stable/release/3.7.1/", "channels/stable/release/latest/"]}"#.into(),
+            ),
+        ]);
+
+        let base_url = utils::serve_archive(routes);
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([
+                    ("versions-url", format!("{base_url}/o?prefix={{channel}}")),
+                    ("latest-url", format!("{base_url}/{{channel}}/VERSION")),
+                ]));
+            })
+            .await;
+
+        let output = plugin.load_versions(LoadVersionsInput::default()).await;
+
+        assert_eq!(
+            output.versions,
+            vec![
+                VersionSpec::parse("3.7.0").unwrap(),
+                VersionSpec::parse("3.7.1").unwrap(),
+                VersionSpec::parse("3.8.0-70.1.beta").unwrap(),
+                VersionSpec::parse("2.19.0-444.1.dev").unwrap(),
+            ]
+        );
+        assert_eq!(
+            output.latest,
+            Some(UnresolvedVersionSpec::parse("3.7.1").unwrap())
+        );
+        assert_eq!(output.aliases.get("latest"), output.latest.as_ref());
+        assert_eq!(
+            output.aliases.get("beta"),
+            Some(&UnresolvedVersionSpec::parse("3.8.0-70.1.beta").unwrap())
+        );
+        assert_eq!(
+            output.aliases.get("dev"),
+            Some(&UnresolvedVersionSpec::parse("2.19.0-444.1.dev").unwrap())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn follows_listing_pages_until_complete() {
+        let mut routes = utils::channel_routes(&[
+            ("stable", "3.7.1", &[]),
+            ("beta", "3.8.0-70.1.beta", &[]),
+            ("dev", "2.19.0-444.1.dev", &[]),
+        ]);
+        routes.extend([
+            (
+                "/o?prefix=stable".into(),
+                r#"{"prefixes": ["channels/stable/release/3.6.0/"], "nextPageToken": "a"}"#.into(),
+            ),
+            // Pages may be empty without being the last one
+            (
+                "/o?prefix=stable&pageToken=a".into(),
+                r#"{"nextPageToken": "b"}"#.into(),
+            ),
+            (
+                "/o?prefix=stable&pageToken=b".into(),
+                r#"{"prefixes": ["channels/stable/release/3.7.0/"], "nextPageToken": "c"}"#.into(),
+            ),
+            (
+                "/o?prefix=stable&pageToken=c".into(),
+                r#"{"prefixes": ["channels/stable/release/3.7.1/"], "nextPageToken": ""}"#.into(),
+            ),
+        ]);
+
+        let base_url = utils::serve_archive(routes);
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([
+                    ("versions-url", format!("{base_url}/o?prefix={{channel}}")),
+                    ("latest-url", format!("{base_url}/{{channel}}/VERSION")),
+                ]));
+            })
+            .await;
+
+        let output = plugin.load_versions(LoadVersionsInput::default()).await;
+
+        assert_eq!(
+            output.versions,
+            vec![
+                VersionSpec::parse("3.6.0").unwrap(),
+                VersionSpec::parse("3.7.0").unwrap(),
+                VersionSpec::parse("3.7.1").unwrap(),
+            ]
+        );
+        assert_eq!(
+            output.latest,
+            Some(UnresolvedVersionSpec::parse("3.7.1").unwrap())
+        );
+    }
+
+    // Lists the stable versions for an arm64 host, without and with an x64 fallback
+    async fn load_arm64_versions(os: HostOS, setting: &str) -> [Vec<VersionSpec>; 2] {
+        let base_url = utils::serve_archive(utils::channel_routes(&[
+            ("stable", "3.7.1", &["2.12.0", "3.7.1"]),
+            ("beta", "3.8.0-70.1.beta", &[]),
+            ("dev", "2.19.0-444.1.dev", &[]),
+        ]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let config = serde_json::json!({
+            "versions-url": format!("{base_url}/o?prefix={{channel}}"),
+            "latest-url": format!("{base_url}/{{channel}}/VERSION"),
+        });
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                builder.host(os, HostArch::Arm64);
+                builder.tool_config(&config);
+            })
+            .await;
+        let plugin_fallback = sandbox
+            .create_plugin_with_config("dart-test", |builder| {
+                let mut config = config.clone();
+                config[setting] = true.into();
+
+                builder.host(os, HostArch::Arm64);
+                builder.tool_config(config);
+            })
+            .await;
+
+        [
+            plugin
+                .load_versions(LoadVersionsInput::default())
+                .await
+                .versions,
+            plugin_fallback
+                .load_versions(LoadVersionsInput::default())
+                .await
+                .versions,
+        ]
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lists_old_versions_with_rosetta_fallback() {
+        assert_eq!(
+            load_arm64_versions(HostOS::MacOS, "rosetta-fallback").await,
+            [
+                vec![VersionSpec::parse("3.7.1").unwrap()],
+                vec![
+                    VersionSpec::parse("2.12.0").unwrap(),
+                    VersionSpec::parse("3.7.1").unwrap()
+                ]
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lists_old_versions_with_windows_x64_fallback() {
+        assert_eq!(
+            load_arm64_versions(HostOS::Windows, "windows-x64-fallback").await,
+            [
+                vec![VersionSpec::parse("3.7.1").unwrap()],
+                vec![
+                    VersionSpec::parse("2.12.0").unwrap(),
+                    VersionSpec::parse("3.7.1").unwrap()
+                ]
+            ]
+        );
+    }
+
+    // Resolves the stable alias and a partial version on a host without prebuilt archives
+    async fn resolve_build_only_versions(os: HostOS, arch: HostArch) -> Vec<VersionSpec> {
+        let base_url = utils::serve_archive(utils::channel_routes(&[
+            ("stable", "3.7.1", &["2.12.0", "3.7.1"]),
+            ("beta", "3.8.0-70.1.beta", &[]),
+            ("dev", "2.19.0-444.1.dev", &[]),
+        ]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let mut versions = vec![];
+
+        for spec in ["stable", "3.7", "2"] {
+            let mut plugin = sandbox
+                .create_plugin_with_config("dart-test", |config| {
+                    config.host(os, arch);
+                    config.tool_config(serde_json::json!({
+                        "versions-url": format!("{base_url}/o?prefix={{channel}}"),
+                        "latest-url": format!("{base_url}/{{channel}}/VERSION"),
+                    }));
+                })
+                .await;
+
+            versions.push(
+                plugin
+                    .tool
+                    .resolve_version(&UnresolvedVersionSpec::parse(spec).unwrap(), false)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        versions
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resolves_versions_on_freebsd() {
+        assert_eq!(
+            resolve_build_only_versions(HostOS::FreeBSD, HostArch::X64).await,
+            [
+                VersionSpec::parse("3.7.1").unwrap(),
+                VersionSpec::parse("3.7.1").unwrap(),
+                VersionSpec::parse("2.12.0").unwrap()
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resolves_versions_on_linux_ppc64() {
+        assert_eq!(
+            resolve_build_only_versions(HostOS::Linux, HostArch::Powerpc64).await,
+            [
+                VersionSpec::parse("3.7.1").unwrap(),
+                VersionSpec::parse("3.7.1").unwrap(),
+                VersionSpec::parse("2.12.0").unwrap()
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn loads_legacy_revision_versions() {
+        let base_url = utils::serve_archive(HashMap::from([
+            ("/stable/latest/VERSION".into(), r#"{"version": "1.24.3"}"#.into()),
+            (
+                "/stable/45396/VERSION".into(),
+                r#"{"revision": "45396", "version": "1.9.3", "date": "201504061344"}"#.into(),
+            ),
+            (
+                "/o?prefix=stable".into(),
+                r#"{"prefixes": ["channels/stable/release/45396/", "channels/stable/release/1.24.3/"]}"#.into(),
+            ),
+            ("/beta/latest/VERSION".into(), r#"{"version": "2.1.0-dev.9.4"}"#.into()),
+            ("/o?prefix=beta".into(), r#"{"prefixes": []}"#.into()),
+            ("/dev/latest/VERSION".into(), r#"{"version": "1.10.0-dev.1.10"}"#.into()),
+            (
+                "/o?prefix=dev".into(),
+                r#"{"prefixes": ["channels/dev/release/45311/"]}"#.into(),
+            ),
+            (
+                "/dev/45311/VERSION".into(),
+                r#"{"revision": "45311", "version": "1.10.0-dev.1.10"}"#.into(),
+            ),
+        ]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([
+                    ("versions-url", format!("{base_url}/o?prefix={{channel}}")),
+                    (
+                        "latest-url",
+                        format!("{base_url}/{{channel}}/latest/VERSION"),
+                    ),
+                    (
+                        "version-file-url",
+                        format!("{base_url}/{{channel}}/{{version}}/VERSION"),
+                    ),
+                ]));
+            })
+            .await;
+
+        let output = plugin.load_versions(LoadVersionsInput::default()).await;
+
+        assert_eq!(
+            output.versions,
+            vec![
+                VersionSpec::parse("1.9.3").unwrap(),
+                VersionSpec::parse("1.24.3").unwrap(),
+                VersionSpec::parse("1.10.0-dev.1.10").unwrap(),
+            ]
+        );
+        assert_eq!(
+            output.aliases.get("dev"),
+            Some(&UnresolvedVersionSpec::parse("1.10.0-dev.1.10").unwrap())
+        );
+    }
+
+    fn legacy_routes() -> HashMap<String, String> {
+        HashMap::from([
+            ("/stable/latest/VERSION".into(), r#"{"version": "1.24.3"}"#.into()),
+            (
+                "/o?prefix=stable".into(),
+                r#"{"prefixes": ["channels/stable/release/45396/", "channels/stable/release/1.24.3/"]}"#.into(),
+            ),
+            ("/beta/latest/VERSION".into(), r#"{"version": "2.1.0-dev.9.4"}"#.into()),
+            ("/o?prefix=beta".into(), r#"{"prefixes": []}"#.into()),
+            ("/dev/latest/VERSION".into(), r#"{"version": "1.10.0-dev.1.10"}"#.into()),
+            ("/o?prefix=dev".into(), r#"{"prefixes": []}"#.into()),
+        ])
+    }
+
+    fn legacy_config(base_url: &str) -> HashMap<&'static str, String> {
+        HashMap::from([
+            ("versions-url", format!("{base_url}/o?prefix={{channel}}")),
+            (
+                "latest-url",
+                format!("{base_url}/{{channel}}/latest/VERSION"),
+            ),
+            (
+                "version-file-url",
+                format!("{base_url}/{{channel}}/{{version}}/VERSION"),
+            ),
+        ])
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches_revision_versions_once() {
+        let mut routes = legacy_routes();
+        routes.insert(
+            "/stable/45396/VERSION".into(),
+            r#"{"revision": "45396", "version": "1.9.3"}"#.into(),
+        );
+
+        let (base_url, requests) = utils::serve_archive_with_log(routes);
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(legacy_config(&base_url));
+            })
+            .await;
+
+        plugin.load_versions(LoadVersionsInput::default()).await;
+        plugin.load_versions(LoadVersionsInput::default()).await;
+
+        let output = plugin
+            .download_prebuilt(DownloadPrebuiltInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("1.9.3").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+
+        assert!(output.download_url.contains("/release/45396/"));
+        assert_eq!(
+            requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|path| path.ends_with("/45396/VERSION"))
+                .count(),
+            1
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "run by skips_unresolvable_revision_dir to capture its warnings"]
+    async fn loads_versions_with_unresolvable_revision_dir() {
+        let base_url = utils::serve_archive(legacy_routes());
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(legacy_config(&base_url));
+            })
+            .await;
+
+        assert_eq!(
+            plugin
+                .load_versions(LoadVersionsInput::default())
+                .await
+                .versions,
+            vec![VersionSpec::parse("1.24.3").unwrap()]
+        );
+    }
+
+    // Host logs are printed to stderr, so the test runs in a child process
+    #[test]
+    fn skips_unresolvable_revision_dir() {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "dart_tool::loads_versions_with_unresolvable_revision_dir",
+                "--exact",
+                "--ignored",
+                "--nocapture",
+            ])
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(output.status.success(), "{stderr}");
+        assert!(
+            stderr.contains("Skipping Dart release 45396 in the stable channel"),
+            "{stderr}"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn loads_versions_from_next_mirror() {
+        let routes = utils::mirror_routes(&[
+            ("stable", "3.7.1", &["3.7.1"]),
+            ("beta", "3.8.0-70.1.beta", &["3.8.0-70.1.beta"]),
+            ("dev", "2.19.0-444.1.dev", &["2.19.0-444.1.dev"]),
+        ]);
+
+        let broken_url = utils::serve_archive(HashMap::new());
+        let base_url = utils::serve_archive(routes);
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Linux, HostArch::X64);
+                config.tool_config(HashMap::from([(
+                    "mirrors",
+                    vec![broken_url.clone(), base_url.clone()],
+                )]));
+            })
+            .await;
+
+        let output = plugin.load_versions(LoadVersionsInput::default()).await;
+
+        assert_eq!(
+            output.versions,
+            vec![
+                VersionSpec::parse("3.7.1").unwrap(),
+                VersionSpec::parse("3.8.0-70.1.beta").unwrap(),
+                VersionSpec::parse("2.19.0-444.1.dev").unwrap(),
+            ]
+        );
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -263,4 +714,133 @@ environment:
             })
             .await;
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(
+        expected = "Enable the windows-x64-fallback setting to use the x64 build under emulation"
+    )]
+    async fn check_versions_windows_arm64_suggests_fallback() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(HostOS::Windows, HostArch::Arm64);
+            })
+            .await;
+
+        let _ = plugin
+            .download_prebuilt(DownloadPrebuiltInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.2.0").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "require glibc and don't run on musl hosts")]
+    async fn check_versions_linux_musl() {
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host_environment(HostEnvironment {
+                    os: HostOS::Linux,
+                    arch: HostArch::X64,
+                    libc: HostLibc::Musl,
+                    ..Default::default()
+                });
+            })
+            .await;
+
+        let _ = plugin
+            .download_prebuilt(DownloadPrebuiltInput {
+                context: ToolContext {
+                    version: VersionSpec::parse("3.7.1").unwrap(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+    }
+
+    async fn resolve_flutter_version(os: HostOS, version: &str) -> ResolveVersionOutput {
+        let base_url = utils::serve_archive(HashMap::from([
+            (
+                "/releases/releases_linux.json".into(),
+                r#"{"releases": [
+                    {"version": "3.24.3", "channel": "stable", "dart_sdk_version": "3.5.3"},
+                    {"version": "3.27.0-0.2.pre", "channel": "beta", "dart_sdk_version": "3.6.0 (build 3.6.0-334.3.beta)"}
+                ]}"#
+                .into(),
+            ),
+            (
+                "/releases/releases_macos.json".into(),
+                r#"{"releases": [{"version": "3.24.3", "dart_sdk_version": "3.5.3"}]}"#.into(),
+            ),
+        ]));
+
+        let sandbox = create_empty_proto_sandbox();
+        let plugin = sandbox
+            .create_plugin_with_config("dart-test", |config| {
+                config.host(os, HostArch::X64);
+                // Mirrors of the Dart archive don't redirect the Flutter release manifest
+                config.tool_config(serde_json::json!({
+                    "flutter-releases-url": format!("{base_url}/releases/releases_{{platform}}.json"),
+                    "mirrors": ["https://dart-mirror.example.com"],
+                }));
+            })
+            .await;
+
+        plugin
+            .resolve_version(ResolveVersionInput {
+                initial: UnresolvedVersionSpec::parse(version).unwrap(),
+                ..Default::default()
+            })
+            .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resolves_flutter_versions_to_bundled_dart() {
+        assert_eq!(
+            resolve_flutter_version(HostOS::Linux, "flutter-3.24.3")
+                .await
+                .candidate,
+            Some(UnresolvedVersionSpec::parse("3.5.3").unwrap())
+        );
+        assert_eq!(
+            resolve_flutter_version(HostOS::Linux, "flutter-3.27.0-0.2.pre")
+                .await
+                .candidate,
+            Some(UnresolvedVersionSpec::parse("3.6.0-334.3.beta").unwrap())
+        );
+        assert_eq!(
+            resolve_flutter_version(HostOS::MacOS, "flutter-3.24.3")
+                .await
+                .candidate,
+            Some(UnresolvedVersionSpec::parse("3.5.3").unwrap())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn leaves_other_versions_unresolved() {
+        assert_eq!(
+            resolve_flutter_version(HostOS::Linux, "stable")
+                .await
+                .candidate,
+            None
+        );
+        assert_eq!(
+            resolve_flutter_version(HostOS::Linux, "3.7.1")
+                .await
+                .candidate,
+            None
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "Unknown Flutter version 1.0.0")]
+    async fn fails_on_unknown_flutter_version() {
+        resolve_flutter_version(HostOS::Linux, "flutter-1.0.0").await;
+    }
 }